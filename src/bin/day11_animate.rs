@@ -0,0 +1,92 @@
+use aoc_2020::day11::automaton::Execution;
+use aoc_2020::day11::recorder::Recording;
+use aoc_2020::day11::{adjacent_seating, sparse_visible_seating, visible_seating, Grid};
+use std::io::{self, Write};
+use std::path::PathBuf;
+use std::time::Duration;
+use std::{env, fs, process};
+
+fn usage() -> ! {
+    eprintln!(
+        "usage: day11_animate [--part <1|2>] [--delay <ms>] [--flipbook] [--stats] \
+         [--ppm <directory>] [--scale <pixels>] [--threads <n>] <file>"
+    );
+    process::exit(1);
+}
+
+fn parse_value<T: std::str::FromStr>(value: Option<String>) -> T {
+    match value.and_then(|value| value.parse().ok()) {
+        Some(value) => value,
+        None => usage(),
+    }
+}
+
+fn main() -> io::Result<()> {
+    let mut part = 1;
+    let mut delay = 200;
+    let mut flipbook = false;
+    let mut stats = false;
+    let mut ppm_directory: Option<PathBuf> = None;
+    let mut scale = 4;
+    let mut threads = None;
+    let mut path = None;
+    let mut args = env::args().skip(1);
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--part" => part = parse_value(args.next()),
+            "--delay" => delay = parse_value(args.next()),
+            "--flipbook" => flipbook = true,
+            "--stats" => stats = true,
+            "--ppm" => ppm_directory = Some(parse_value(args.next())),
+            "--scale" => scale = parse_value(args.next()),
+            "--threads" => threads = Some(parse_value(args.next())),
+            _ if path.is_none() => path = Some(arg),
+            _ => usage(),
+        }
+    }
+
+    let path = path.unwrap_or_else(|| usage());
+    let grid = match fs::read_to_string(path)?.parse::<Grid>() {
+        Ok(grid) => grid,
+        Err(()) => {
+            eprintln!("invalid seat layout");
+            process::exit(1);
+        }
+    };
+    let execution = threads.map_or(Execution::Sequential, |threads| Execution::Parallel {
+        threads,
+    });
+    let recording = match (part, threads) {
+        (1, _) => {
+            let mut automaton = adjacent_seating(&grid);
+            automaton.set_execution(execution);
+            Recording::until_stable(&mut automaton)
+        }
+        // the sparse automaton only runs on one thread
+        (2, None) => Recording::until_stable(&mut sparse_visible_seating(&grid)),
+        (2, Some(_)) => {
+            let mut automaton = visible_seating(&grid);
+            automaton.set_execution(execution);
+            Recording::until_stable(&mut automaton)
+        }
+        _ => usage(),
+    };
+
+    let stdout = io::stdout();
+    let mut stdout = stdout.lock();
+    if let Some(directory) = ppm_directory {
+        fs::create_dir_all(&directory)?;
+        let paths = recording.save_ppm_frames(&directory, scale)?;
+        eprintln!("wrote {} frames to {}", paths.len(), directory.display());
+    }
+    if stats {
+        for round in recording.stats() {
+            writeln!(stdout, "{}", round)?;
+        }
+    } else if flipbook {
+        recording.write_flipbook(&mut stdout)?;
+    } else {
+        recording.replay(&mut stdout, Duration::from_millis(delay))?;
+    }
+    Ok(())
+}
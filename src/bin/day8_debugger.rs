@@ -0,0 +1,18 @@
+use aoc_2020::day8::debugger::{repl, Debugger};
+use aoc_2020::day8::{parse_input_day8, VM};
+use std::{env, fs, io, process};
+
+fn main() -> io::Result<()> {
+    let path = match env::args().nth(1) {
+        Some(path) => path,
+        None => {
+            eprintln!("usage: day8_debugger <program file>");
+            process::exit(1);
+        }
+    };
+    let input = fs::read_to_string(path)?;
+    let mut debugger = Debugger::new(VM::from_instructions(&parse_input_day8(&input)));
+
+    let stdin = io::stdin();
+    repl(&mut debugger, stdin.lock(), io::stdout())
+}
@@ -0,0 +1,45 @@
+use aoc_2020::day9::{validate_stream, DEFAULT_PREAMBLE_SIZE};
+use std::fs::File;
+use std::io::{self, BufRead, BufReader, Write};
+use std::{env, process};
+
+fn usage() -> ! {
+    eprintln!("usage: day9_validate [--preamble <size>] [file]");
+    process::exit(1);
+}
+
+fn main() -> io::Result<()> {
+    let mut preamble_size = DEFAULT_PREAMBLE_SIZE;
+    let mut path = None;
+    let mut args = env::args().skip(1);
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--preamble" | "-p" => {
+                preamble_size = match args
+                    .next()
+                    .and_then(|size| size.parse().ok())
+                    .filter(|size| *size > 0)
+                {
+                    Some(size) => size,
+                    None => usage(),
+                }
+            }
+            _ if path.is_none() => path = Some(arg),
+            _ => usage(),
+        }
+    }
+
+    // reads from stdin if no file is given, so unbounded feeds can be piped in
+    let reader: Box<dyn BufRead> = match path {
+        Some(path) => Box::new(BufReader::new(File::open(path)?)),
+        None => Box::new(BufReader::new(io::stdin())),
+    };
+
+    let stdout = io::stdout();
+    let mut stdout = stdout.lock();
+    for violation in validate_stream(reader, preamble_size)? {
+        let violation = violation?;
+        writeln!(stdout, "{}\t{}", violation.index, violation.value)?;
+    }
+    Ok(())
+}
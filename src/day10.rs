@@ -1,51 +1,392 @@
+use crate::graph::Graph;
 use aoc_runner_derive::{aoc, aoc_generator};
+use core::fmt;
 use std::cmp::max;
-use std::collections::{BTreeSet, HashMap};
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt::{Display, Formatter};
 
-struct DirectedAcyclicGraph {
+/// Which joltage differences an adapter accepts between its input and output.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Tolerance {
+    min_gap: usize,
+    max_gap: usize,
+}
+
+impl Tolerance {
+    /// `None` unless `1 <= min_gap <= max_gap`, a gap of 0 would connect an adapter to itself.
+    pub fn new(min_gap: usize, max_gap: usize) -> Option<Self> {
+        if min_gap >= 1 && min_gap <= max_gap {
+            Some(Tolerance { min_gap, max_gap })
+        } else {
+            None
+        }
+    }
+
+    pub fn min_gap(&self) -> usize {
+        self.min_gap
+    }
+
+    pub fn max_gap(&self) -> usize {
+        self.max_gap
+    }
+}
+
+impl Default for Tolerance {
+    fn default() -> Self {
+        Tolerance {
+            min_gap: 1,
+            max_gap: 3,
+        }
+    }
+}
+
+pub struct DirectedAcyclicGraph {
+    /// the joltages in ascending order
     nodes: BTreeSet<usize>,
-    edges: HashMap<usize, Vec<usize>>,
+    graph: Graph<usize>,
+    tolerance: Tolerance,
 }
 
 impl DirectedAcyclicGraph {
-    fn new() -> Self {
+    fn new(tolerance: Tolerance) -> Self {
         DirectedAcyclicGraph {
             nodes: BTreeSet::new(),
-            edges: HashMap::new(),
+            graph: Graph::new(),
+            tolerance,
         }
     }
 
-    fn add_edge(&mut self, input_joltage: usize, output_joltage: usize) {
-        if let Some(adapters) = self.edges.get_mut(&input_joltage) {
-            adapters.push(output_joltage);
-        } else {
-            let mut adapters = Vec::new();
-            adapters.push(output_joltage);
-            self.edges.insert(input_joltage, adapters);
+    /// Adds an adapter and connects it to all adapters within the tolerance.
+    ///
+    /// Looks up the neighbors by range, so the cost does not depend on the size of the gaps.
+    fn add_node(&mut self, node: usize) {
+        if !self.nodes.insert(node) {
+            return;
+        }
+        self.graph.add_node(node);
+        let Tolerance { min_gap, max_gap } = self.tolerance;
+        if let Some(highest) = node.checked_sub(min_gap) {
+            for &input in self.nodes.range(node.saturating_sub(max_gap)..=highest) {
+                self.graph.add_edge(input, node, ());
+            }
+        }
+        if let Some(lowest) = node.checked_add(min_gap) {
+            for &output in self.nodes.range(lowest..=node.saturating_add(max_gap)) {
+                self.graph.add_edge(node, output, ());
+            }
         }
     }
 
-    fn add_node(&mut self, node: usize) {
-        self.nodes.insert(node);
+    /// Counts how often each joltage difference occurs when using all adapters,
+    /// `None` if some difference is not within the tolerance.
+    pub fn joltage_histogram(&self) -> Option<BTreeMap<usize, usize>> {
+        let mut differences = BTreeMap::new();
+        let mut current = 0;
+        for adapter in self.nodes.iter().skip(1) {
+            let difference = adapter - current;
+            if difference < self.tolerance.min_gap || difference > self.tolerance.max_gap {
+                return None;
+            }
+            *differences.entry(difference).or_insert(0) += 1;
+            current = *adapter;
+        }
+        Some(differences)
+    }
+
+    /// the power outlet
+    pub fn outlet(&self) -> usize {
+        *self.nodes.iter().next().unwrap()
+    }
+
+    /// the device, which is always the node with the highest joltage
+    pub fn device(&self) -> usize {
+        *self.nodes.iter().next_back().unwrap()
+    }
+
+    fn neighbors(&self, node: usize) -> impl Iterator<Item = usize> + '_ {
+        self.graph.successors(&node).map(|(neighbor, _)| *neighbor)
+    }
+
+    /// Counts the paths from the outlet to the device, fails as soon as `add` does.
+    fn count_paths<T: Clone>(
+        &self,
+        zero: T,
+        one: T,
+        add: impl Fn(&T, &T) -> Option<T>,
+    ) -> Option<T> {
+        let device = self.device();
+        let counts = self
+            .graph
+            .fold(|node, neighbors| {
+                if *node == device {
+                    return Some(one.clone());
+                }
+                // a dead end stays at zero, the device cannot be reached from there
+                neighbors
+                    .iter()
+                    .try_fold(zero.clone(), |sum, (_, _, count)| {
+                        add(&sum, count.as_ref()?)
+                    })
+            })
+            .expect("edges always increase the joltage");
+        counts[&self.outlet()].clone()
+    }
+
+    pub fn count_arrangements(&self) -> Arrangements {
+        match self.count_paths(0u128, 1u128, |a, b| a.checked_add(*b)) {
+            Some(count) => Arrangements::Exact(count),
+            None => Arrangements::Big(
+                self.count_paths(BigUint::from(0), BigUint::from(1), |a, b| Some(a.add(b)))
+                    .unwrap(),
+            ),
+        }
+    }
+
+    /// Lazily enumerates all chains of adapters from the outlet to the device.
+    pub fn arrangements(&self) -> Paths<'_> {
+        Paths {
+            dag: self,
+            stack: vec![(self.outlet(), 0)],
+        }
+    }
+
+    /// nodes that can be reached from the outlet
+    fn reachable(&self) -> BTreeSet<usize> {
+        let mut reachable = self
+            .graph
+            .reachable_from(&self.outlet())
+            .into_iter()
+            .copied()
+            .collect::<BTreeSet<usize>>();
+        reachable.insert(self.outlet());
+        reachable
+    }
+
+    /// nodes from which the device can be reached
+    fn reaches_device(&self) -> BTreeSet<usize> {
+        let mut reaches = self
+            .graph
+            .reaching(&self.device())
+            .into_iter()
+            .copied()
+            .collect::<BTreeSet<usize>>();
+        reaches.insert(self.device());
+        reaches
+    }
+
+    /// Finds the chain from the outlet to the device using the fewest (`longest == false`)
+    /// or the most adapters, `None` if the device cannot be reached.
+    fn extreme_chain(&self, longest: bool) -> Option<Vec<usize>> {
+        let device = self.device();
+        // (number of adapters to the device, next adapter) for every node that reaches the device
+        let best = self
+            .graph
+            .fold(|node, neighbors| {
+                if *node == device {
+                    return Some((0, device));
+                }
+                let candidates = neighbors
+                    .iter()
+                    .filter_map(|(neighbor, _, best)| Some((best.as_ref()?.0 + 1, **neighbor)));
+                if longest {
+                    candidates.max()
+                } else {
+                    candidates.min()
+                }
+            })
+            .expect("edges always increase the joltage");
+
+        let mut chain = vec![self.outlet()];
+        let mut current = self.outlet();
+        while current != device {
+            current = best[&current]?.1;
+            chain.push(current);
+        }
+        Some(chain)
+    }
+
+    pub fn shortest_chain(&self) -> Option<Vec<usize>> {
+        self.extreme_chain(false)
+    }
+
+    pub fn longest_chain(&self) -> Option<Vec<usize>> {
+        self.extreme_chain(true)
+    }
+
+    /// Adapters that are part of every arrangement, i.e. no usable edge skips them.
+    pub fn articulation_adapters(&self) -> Vec<usize> {
+        let reachable = self.reachable();
+        let reaches_device = self.reaches_device();
+        if !reachable.contains(&self.device()) {
+            return Vec::new();
+        }
+        let useful = reachable
+            .intersection(&reaches_device)
+            .copied()
+            .collect::<Vec<usize>>();
+
+        // the furthest node an edge from any earlier useful node leads to
+        let mut furthest = 0;
+        let mut articulation = Vec::new();
+        for node in &useful {
+            if *node >= furthest && *node != self.outlet() && *node != self.device() {
+                articulation.push(*node);
+            }
+            for neighbor in self.neighbors(*node) {
+                if reaches_device.contains(&neighbor) {
+                    // an edge only skips the nodes strictly between its ends
+                    furthest = max(furthest, neighbor);
+                }
+            }
+        }
+        articulation
+    }
+
+    /// Joltages of additional adapters that would add arrangements, restoring the
+    /// connection if the device cannot be reached. The device rating stays the same.
+    pub fn missing_adapters(&self) -> Vec<usize> {
+        let reachable = self.reachable();
+        let reaches_device = self.reaches_device();
+        let Tolerance { min_gap, max_gap } = self.tolerance;
+
+        (self.outlet() + 1..self.device())
+            .filter(|joltage| !self.nodes.contains(joltage))
+            .filter(|joltage| {
+                // the outlet is no predecessor if the gap to it is too small
+                let from = match joltage.checked_sub(min_gap) {
+                    Some(highest) => joltage.saturating_sub(max_gap)..=highest,
+                    None => return false,
+                };
+                let to = (joltage + min_gap)..=joltage.saturating_add(max_gap);
+                reachable.range(from).next().is_some() && reaches_device.range(to).next().is_some()
+            })
+            .collect()
+    }
+}
+
+/// Depth-first iterator over all arrangements, see [`DirectedAcyclicGraph::arrangements`].
+pub struct Paths<'a> {
+    dag: &'a DirectedAcyclicGraph,
+    /// the current path with the index of the next neighbor to visit for each node
+    stack: Vec<(usize, usize)>,
+}
+
+impl Iterator for Paths<'_> {
+    type Item = Vec<usize>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        while let Some((node, next_neighbor)) = self.stack.last_mut() {
+            let node = *node;
+            if node == self.dag.device() {
+                let path = self.stack.iter().map(|(node, _)| *node).collect();
+                self.stack.pop();
+                return Some(path);
+            }
+            match self.dag.neighbors(node).nth(*next_neighbor) {
+                Some(neighbor) => {
+                    *next_neighbor += 1;
+                    self.stack.push((neighbor, 0));
+                }
+                None => {
+                    self.stack.pop();
+                }
+            }
+        }
+        None
+    }
+}
+
+/// Unsigned integer of arbitrary size, just enough to count arrangements.
+#[derive(Clone, Debug, PartialEq)]
+pub struct BigUint {
+    /// base 2^64 digits, least significant first, without trailing zeros
+    limbs: Vec<u64>,
+}
+
+impl From<u128> for BigUint {
+    fn from(n: u128) -> Self {
+        let mut limbs = vec![n as u64, (n >> 64) as u64];
+        while limbs.last() == Some(&0) {
+            limbs.pop();
+        }
+        BigUint { limbs }
     }
 }
 
-fn add_adapter(dag: &mut DirectedAcyclicGraph, output_joltage: usize) {
-    dag.add_node(output_joltage);
-    if output_joltage >= 1 {
-        dag.add_edge(output_joltage - 1, output_joltage);
+impl BigUint {
+    pub fn add(&self, other: &BigUint) -> BigUint {
+        let mut limbs = Vec::with_capacity(max(self.limbs.len(), other.limbs.len()) + 1);
+        let mut carry = false;
+        for i in 0..max(self.limbs.len(), other.limbs.len()) {
+            let a = self.limbs.get(i).copied().unwrap_or(0);
+            let b = other.limbs.get(i).copied().unwrap_or(0);
+            let (sum, overflow_1) = a.overflowing_add(b);
+            let (sum, overflow_2) = sum.overflowing_add(carry as u64);
+            limbs.push(sum);
+            carry = overflow_1 || overflow_2;
+        }
+        if carry {
+            limbs.push(1);
+        }
+        BigUint { limbs }
     }
-    if output_joltage >= 2 {
-        dag.add_edge(output_joltage - 2, output_joltage);
+
+    /// Divides in place and returns the remainder.
+    fn div_rem(&mut self, divisor: u64) -> u64 {
+        let mut remainder = 0u128;
+        for limb in self.limbs.iter_mut().rev() {
+            let current = (remainder << 64) | *limb as u128;
+            *limb = (current / divisor as u128) as u64;
+            remainder = current % divisor as u128;
+        }
+        while self.limbs.last() == Some(&0) {
+            self.limbs.pop();
+        }
+        remainder as u64
     }
-    if output_joltage >= 3 {
-        dag.add_edge(output_joltage - 3, output_joltage);
+}
+
+impl Display for BigUint {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        const CHUNK: u64 = 10_000_000_000_000_000_000;
+
+        let mut n = self.clone();
+        let mut chunks = Vec::new();
+        while !n.limbs.is_empty() {
+            chunks.push(n.div_rem(CHUNK));
+        }
+        match chunks.pop() {
+            Some(most_significant) => write!(f, "{}", most_significant)?,
+            None => return f.write_str("0"),
+        }
+        for chunk in chunks.iter().rev() {
+            write!(f, "{:019}", chunk)?;
+        }
+        Ok(())
     }
 }
 
-#[aoc_generator(day10)]
-fn parse_input_day10(input: &str) -> DirectedAcyclicGraph {
-    let mut dag = DirectedAcyclicGraph::new();
+/// Number of adapter arrangements, only falling back to a big integer if it exceeds `u128`.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Arrangements {
+    Exact(u128),
+    Big(BigUint),
+}
+
+impl Display for Arrangements {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            Arrangements::Exact(n) => n.fmt(f),
+            Arrangements::Big(n) => n.fmt(f),
+        }
+    }
+}
+
+/// Builds the graph of the adapters in `input` plus the outlet and the device.
+///
+/// Panics if a line is not a joltage or the device rating does not fit into `usize`.
+pub fn parse_adapters(input: &str, tolerance: Tolerance) -> DirectedAcyclicGraph {
+    let mut dag = DirectedAcyclicGraph::new(tolerance);
 
     // add the start node (power outlet)
     dag.add_node(0);
@@ -55,50 +396,33 @@ fn parse_input_day10(input: &str) -> DirectedAcyclicGraph {
     for line in input.lines() {
         let joltage = line.parse().unwrap();
         max_joltage = max(max_joltage, joltage);
-        add_adapter(&mut dag, joltage);
+        dag.add_node(joltage);
     }
 
     // add the end node (device)
-    let device_rating = max_joltage + 3;
-    add_adapter(&mut dag, device_rating);
+    let device_rating = max_joltage
+        .checked_add(tolerance.max_gap)
+        .expect("the device rating does not fit into usize");
+    dag.add_node(device_rating);
 
     dag
 }
 
+#[aoc_generator(day10)]
+fn parse_input_day10(input: &str) -> DirectedAcyclicGraph {
+    parse_adapters(input, Tolerance::default())
+}
+
 #[aoc(day10, part1)]
 fn part1(adapters: &DirectedAcyclicGraph) -> Option<usize> {
-    let mut differences = [0; 3];
-    let mut current = 0;
-    for adapter in adapters.nodes.iter().skip(1) {
-        let difference = adapter - current;
-        match difference {
-            1 | 2 | 3 => differences[difference - 1] += 1,
-            _ => return None,
-        }
-        current = *adapter;
-    }
-
-    Some(differences[0] * differences[2])
+    let differences = adapters.joltage_histogram()?;
+    let count = |difference| differences.get(&difference).copied().unwrap_or(0);
+    Some(count(1) * count(3))
 }
 
 #[aoc(day10, part2)]
-fn part2(adapters: &DirectedAcyclicGraph) -> usize {
-    let mut factors: HashMap<usize, usize> = HashMap::new();
-
-    let mut permutations = 0;
-    for adapter in adapters.nodes.iter().rev() {
-        if let Some(neighbors) = adapters.edges.get(adapter) {
-            permutations = 0;
-            for neighbor in neighbors {
-                permutations += factors.get(neighbor).unwrap();
-            }
-        } else {
-            permutations = 1;
-        }
-        factors.insert(*adapter, permutations);
-    }
-
-    permutations
+fn part2(adapters: &DirectedAcyclicGraph) -> Arrangements {
+    adapters.count_arrangements()
 }
 
 #[cfg(test)]
@@ -160,10 +484,162 @@ mod tests {
 
     #[test]
     fn part2_small_example() {
-        assert_eq!(part2(&parse_input_day10(INPUT_SMALL)), 8);
+        assert_eq!(
+            part2(&parse_input_day10(INPUT_SMALL)),
+            Arrangements::Exact(8)
+        );
     }
     #[test]
     fn part2_large_example() {
-        assert_eq!(part2(&parse_input_day10(INPUT_LARGE)), 19208);
+        assert_eq!(
+            part2(&parse_input_day10(INPUT_LARGE)),
+            Arrangements::Exact(19208)
+        );
+    }
+
+    fn chain(length: usize) -> String {
+        (1..=length)
+            .map(|joltage| joltage.to_string())
+            .collect::<Vec<String>>()
+            .join("\n")
+    }
+
+    #[test]
+    fn custom_tolerance() {
+        assert_eq!(Tolerance::new(0, 3), None);
+        assert_eq!(Tolerance::new(3, 2), None);
+        assert_eq!(Tolerance::new(1, 3), Some(Tolerance::default()));
+
+        let tolerance = Tolerance::new(2, 4).unwrap();
+        let adapters = parse_adapters("2\n4\n5\n7\n9", tolerance);
+        // 0 -> 2 -> 4 -> 7 -> 9, 0 -> 2 -> 5 -> 7 -> 9, 0 -> 2 -> 5 -> 9 and 0 -> 4 -> 7 -> 9
+        assert_eq!(adapters.joltage_histogram(), None);
+        assert_eq!(adapters.count_arrangements(), Arrangements::Exact(4));
+
+        let adapters = parse_adapters("2\n4\n8\n10", tolerance);
+        assert_eq!(
+            adapters.joltage_histogram(),
+            Some(vec![(2, 3), (4, 2)].into_iter().collect())
+        );
+        assert_eq!(part1(&adapters), Some(0));
+    }
+
+    #[test]
+    fn large_gap() {
+        let tolerance = Tolerance::new(1, usize::MAX / 2).unwrap();
+        // only the highest adapter is close enough to the device, 3 and 5 are optional
+        let adapters = parse_adapters("5\n1000000\n3", tolerance);
+        assert_eq!(adapters.device(), 1_000_000 + usize::MAX / 2);
+        assert_eq!(adapters.count_arrangements(), Arrangements::Exact(4));
+        assert_eq!(adapters.joltage_histogram().map(|h| h.len()), Some(4));
+
+        let tolerance = Tolerance::new(usize::MAX, usize::MAX).unwrap();
+        let adapters = parse_adapters("", tolerance);
+        assert_eq!(adapters.device(), usize::MAX);
+        assert_eq!(adapters.count_arrangements(), Arrangements::Exact(1));
+    }
+
+    #[test]
+    fn long_chain_fits_u128() {
+        // every adapter can skip up to two of the following ones
+        let mut ways = [1u128, 1, 0];
+        for _ in 0..119 {
+            ways = [ways[0] + ways[1] + ways[2], ways[0], ways[1]];
+        }
+        assert_eq!(
+            part2(&parse_input_day10(&chain(120))),
+            Arrangements::Exact(ways[0])
+        );
+    }
+
+    #[test]
+    fn long_chain_overflows_u128() {
+        let mut ways = [BigUint::from(1), BigUint::from(1), BigUint::from(0)];
+        for _ in 0..299 {
+            let next = ways[0].add(&ways[1]).add(&ways[2]);
+            ways = [next, ways[0].clone(), ways[1].clone()];
+        }
+        assert_eq!(
+            part2(&parse_input_day10(&chain(300))),
+            Arrangements::Big(ways[0].clone())
+        );
+    }
+
+    #[test]
+    fn enumerate_arrangements() {
+        let adapters = parse_input_day10(INPUT_SMALL);
+        let mut arrangements = adapters.arrangements().collect::<Vec<Vec<usize>>>();
+        arrangements.sort();
+        assert_eq!(arrangements.len(), 8);
+        assert_eq!(
+            arrangements[0],
+            vec![0, 1, 4, 5, 6, 7, 10, 11, 12, 15, 16, 19, 22]
+        );
+        assert_eq!(adapters.arrangements().take(3).count(), 3);
+    }
+
+    #[test]
+    fn shortest_and_longest_chain() {
+        let adapters = parse_input_day10(INPUT_SMALL);
+        assert_eq!(
+            adapters.shortest_chain(),
+            Some(vec![0, 1, 4, 7, 10, 12, 15, 16, 19, 22])
+        );
+        assert_eq!(
+            adapters.longest_chain(),
+            Some(vec![0, 1, 4, 5, 6, 7, 10, 11, 12, 15, 16, 19, 22])
+        );
+        assert_eq!(parse_input_day10("1\n10").shortest_chain(), None);
+    }
+
+    #[test]
+    fn articulation_adapters() {
+        let adapters = parse_input_day10(INPUT_SMALL);
+        assert_eq!(
+            adapters.articulation_adapters(),
+            vec![1, 4, 7, 10, 12, 15, 16, 19]
+        );
+    }
+
+    #[test]
+    fn broken_chain() {
+        let adapters = parse_input_day10("1\n2\n8\n9");
+        assert_eq!(part1(&adapters), None);
+        assert_eq!(part2(&adapters), Arrangements::Exact(0));
+        assert_eq!(adapters.arrangements().count(), 0);
+        assert_eq!(adapters.missing_adapters(), vec![5]);
+
+        let restored = parse_input_day10("1\n2\n5\n8\n9");
+        assert_eq!(part1(&restored), Some(3 * 3));
+        assert_eq!(restored.missing_adapters(), vec![3, 4, 6, 7, 10, 11]);
+    }
+
+    #[test]
+    fn missing_adapters_respect_min_gap() {
+        let tolerance = Tolerance::new(2, 2).unwrap();
+        assert_eq!(parse_adapters("3", tolerance).missing_adapters(), vec![]);
+        assert_eq!(
+            parse_adapters("1\n3", tolerance).count_arrangements(),
+            Arrangements::Exact(0)
+        );
+
+        let tolerance = Tolerance::new(2, 3).unwrap();
+        let adapters = parse_adapters("5", tolerance);
+        assert_eq!(adapters.missing_adapters(), vec![2, 3]);
+        for missing in &[2, 3] {
+            let restored = parse_adapters(&format!("{}\n5", missing), tolerance);
+            assert_eq!(restored.count_arrangements(), Arrangements::Exact(1));
+        }
+    }
+
+    #[test]
+    fn big_uint_display() {
+        let n = BigUint::from(u128::MAX).add(&BigUint::from(1));
+        assert_eq!(n.to_string(), "340282366920938463463374607431768211456");
+        assert_eq!(BigUint::from(0).to_string(), "0");
+        assert_eq!(
+            BigUint::from(10_000_000_000_000_000_000).to_string(),
+            "10000000000000000000"
+        );
     }
 }
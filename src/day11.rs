@@ -1,9 +1,16 @@
 use aoc_runner_derive::{aoc, aoc_generator};
-use std::fmt::{Display, Formatter, Write};
+use automaton::{Automaton, Execution, Neighborhood, Rule};
 use core::fmt;
+use sparse::{NeighborGraph, SparseAutomaton};
+use std::fmt::{Display, Formatter, Write};
+use std::str::FromStr;
+
+pub mod automaton;
+pub mod recorder;
+pub mod sparse;
 
-#[derive(Copy, Clone, Eq, PartialEq)]
-enum Seat {
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash)]
+pub enum Seat {
     Floor,
     Empty,
     Occupied,
@@ -30,149 +37,191 @@ impl Seat {
     }
 }
 
-type Seats = Vec<Vec<Seat>>;
+/// The seat layout, stored row by row.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct Grid {
+    width: usize,
+    height: usize,
+    cells: Vec<Seat>,
+}
 
-#[aoc_generator(day11)]
-fn parse_input_day11(input: &str) -> Seats {
-    input
-        .lines()
-        .map(|line| {
-            line
-                .chars()
-                .map(|c| Seat::from_char(&c).unwrap())
-                .collect()
+impl Grid {
+    /// `None` if the rows do not all have the same length
+    pub fn new(rows: &[Vec<Seat>]) -> Option<Self> {
+        let width = rows.first().map_or(0, |row| row.len());
+        if rows.iter().any(|row| row.len() != width) {
+            return None;
+        }
+        Some(Grid {
+            width,
+            height: rows.len(),
+            cells: rows.concat(),
         })
-        .collect()
-}
-
-fn count_adjacent_occupied_seats(seats: &Seats, y: usize, x: usize) -> usize {
-    const OFFSETS: [(i32, i32); 8] = [
-        (-1, -1),
-        (-1, 0),
-        (-1, 1),
-        (0, -1),
-        (0, 1),
-        (1, -1),
-        (1, 0),
-        (1, 1),
-    ];
-    let mut counter = 0;
-    for (dx, dy) in OFFSETS.iter() {
-        if let Some(current_y) = (y as i32).checked_add(*dy) {
-            let current_y = current_y as usize;
-            if !(0..seats.len()).contains(&current_y) {
-                continue
-            }
-            if let Some(current_x) = (x as i32).checked_add(*dx) {
-                let current_x = current_x as usize;
-                if !(0..seats[current_y].len()).contains(&current_x) {
-                    continue;
-                }
-                if seats[current_y][current_x] == Seat::Occupied {
-                    counter += 1;
-                }
-            }
+    }
+
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    pub fn cells(&self) -> &[Seat] {
+        &self.cells
+    }
+
+    /// (y, x) of the seat at `index`
+    pub fn position(&self, index: usize) -> (usize, usize) {
+        (index / self.width, index % self.width)
+    }
+
+    pub fn get(&self, y: isize, x: isize) -> Option<Seat> {
+        if y < 0 || x < 0 || y as usize >= self.height || x as usize >= self.width {
+            return None;
         }
+        Some(self.cells[y as usize * self.width + x as usize])
+    }
+
+    pub fn occupied(&self) -> usize {
+        self.cells
+            .iter()
+            .filter(|seat| **seat == Seat::Occupied)
+            .count()
+    }
+}
+
+impl FromStr for Grid {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let rows = s
+            .lines()
+            .map(|line| line.chars().map(|c| Seat::from_char(&c)).collect())
+            .collect::<Result<Vec<Vec<Seat>>, ()>>()?;
+        Grid::new(&rows).ok_or(())
     }
-    counter
-}
-
-fn simulate_round(seats: &Seats, count_occupied_seats: fn(&Seats, usize, usize) -> usize, occupied_threshold: usize) -> (Vec<Vec<Seat>>, bool) {
-    let mut result = Vec::new();
-    let mut changed = false;
-    for (y, row) in seats.iter().enumerate() {
-        let mut result_row = Vec::new();
-        for (x, seat) in row.iter().enumerate() {
-            let adjacent_occupied = count_occupied_seats(seats, y, x);
-            if *seat == Seat::Empty && adjacent_occupied == 0 {
-                result_row.push(Seat::Occupied);
-                changed = true;
-            } else if *seat == Seat::Occupied && adjacent_occupied >= occupied_threshold {
-                result_row.push(Seat::Empty);
-                changed = true;
-            } else {
-                result_row.push(*seat);
+}
+
+impl Display for Grid {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        for row in self.cells.chunks(self.width.max(1)) {
+            for seat in row {
+                seat.fmt(f)?;
             }
+            f.write_char('\n')?;
         }
-        result.push(result_row);
+        Ok(())
     }
-    (result, changed)
 }
 
-#[aoc(day11, part1)]
-fn part1(seats: &Seats) -> usize {
-    let mut current_seats = seats.clone();
-    loop {
-        let (round_seats, changed) = simulate_round(&current_seats, count_adjacent_occupied_seats, 4);
-        if !changed {
-            break;
-        }
-        current_seats = round_seats;
-    }
-
-    current_seats.iter().flatten().filter(|seat| **seat == Seat::Occupied).count()
-}
-
-fn count_visible_occupied_seats(seats: &Seats, y: usize, x: usize) -> usize {
-    const OFFSETS: [(i32, i32); 8] = [
-        (-1, -1),
-        (-1, 0),
-        (-1, 1),
-        (0, -1),
-        (0, 1),
-        (1, -1),
-        (1, 0),
-        (1, 1),
-    ];
-
-    OFFSETS
-        .iter()
-        .map(|(dx, dy)| {
-            let mut factor = 1;
-            loop {
-                if let Some(current_y) = (y as i32).checked_add(*dy * factor) {
-                    let current_y = current_y as usize;
-                    if !(0..seats.len()).contains(&current_y) {
-                        return 0;
-                    }
-                    if let Some(current_x) = (x as i32).checked_add(*dx * factor) {
-                        let current_x = current_x as usize;
-                        if !(0..seats[current_y].len()).contains(&current_x) {
-                            return 0;
-                        }
-
-                        match seats[current_y][current_x] {
-                            Seat::Occupied => return 1,
-                            Seat::Empty => return 0,
-                            _ => {},
-                        }
+#[aoc_generator(day11)]
+fn parse_input_day11(input: &str) -> Grid {
+    input.parse().unwrap()
+}
+
+const DIRECTIONS: [(isize, isize); 8] = [
+    (-1, -1),
+    (-1, 0),
+    (-1, 1),
+    (0, -1),
+    (0, 1),
+    (1, -1),
+    (1, 0),
+    (1, 1),
+];
+
+/// the eight seats around a seat
+pub struct Adjacent;
+
+impl Neighborhood for Adjacent {
+    fn occupied_neighbors(&self, grid: &Grid, index: usize) -> usize {
+        let (y, x) = grid.position(index);
+        DIRECTIONS
+            .iter()
+            .filter(|(dy, dx)| grid.get(y as isize + dy, x as isize + dx) == Some(Seat::Occupied))
+            .count()
+    }
+}
+
+/// the first seat in each of the eight directions, looking across the floor
+pub struct LineOfSight;
+
+impl Neighborhood for LineOfSight {
+    fn occupied_neighbors(&self, grid: &Grid, index: usize) -> usize {
+        let (y, x) = grid.position(index);
+        DIRECTIONS
+            .iter()
+            .filter(|(dy, dx)| {
+                let mut factor = 1;
+                loop {
+                    match grid.get(y as isize + dy * factor, x as isize + dx * factor) {
+                        Some(Seat::Floor) => factor += 1,
+                        seat => return seat == Some(Seat::Occupied),
                     }
                 }
-                factor += 1;
-            }
-        })
-        .sum()
+            })
+            .count()
+    }
+}
+
+/// Sit down next to nobody, leave if 4 or more adjacent seats are occupied.
+pub fn adjacent_seating(seats: &Grid) -> Automaton<Adjacent> {
+    Automaton::new(seats.clone(), Adjacent, "B0/S0123".parse().unwrap())
+}
+
+fn visible_rule() -> Rule {
+    "B0/S01234".parse().unwrap()
+}
+
+/// Sit down if no occupied seat is visible, leave if 5 or more are.
+pub fn visible_seating(seats: &Grid) -> Automaton<LineOfSight> {
+    Automaton::new(seats.clone(), LineOfSight, visible_rule())
+}
+
+/// Same as [`visible_seating`], but with the visible seats looked up once
+/// and only evaluating seats whose neighborhood changed.
+pub fn sparse_visible_seating(seats: &Grid) -> SparseAutomaton {
+    SparseAutomaton::new(
+        seats.clone(),
+        NeighborGraph::line_of_sight(seats),
+        visible_rule(),
+    )
+}
+
+#[aoc(day11, part1)]
+fn part1(seats: &Grid) -> usize {
+    let mut automaton = adjacent_seating(seats);
+    automaton.run_until_stable();
+    automaton.grid().occupied()
 }
 
 #[aoc(day11, part2)]
-fn part2(seats: &Seats) -> usize {
-    let mut current_seats = seats.clone();
-    loop {
-        let (round_seats, changed) = simulate_round(&current_seats, count_visible_occupied_seats, 5);
-        if !changed {
-            break;
-        }
-        current_seats = round_seats;
-    }
+fn part2(seats: &Grid) -> usize {
+    let mut automaton = sparse_visible_seating(seats);
+    automaton.run_until_stable();
+    automaton.grid().occupied()
+}
 
-    current_seats.iter().flatten().filter(|seat| **seat == Seat::Occupied).count()
+#[aoc(day11, part1, parallel)]
+fn part1_parallel(seats: &Grid) -> usize {
+    let mut automaton = adjacent_seating(seats);
+    automaton.set_execution(Execution::available_parallelism());
+    automaton.run_until_stable();
+    automaton.grid().occupied()
 }
 
-#[cfg(test)]
-mod tests {
-    use super::*;
+#[aoc(day11, part2, parallel)]
+fn part2_parallel(seats: &Grid) -> usize {
+    let mut automaton = visible_seating(seats);
+    automaton.set_execution(Execution::available_parallelism());
+    automaton.run_until_stable();
+    automaton.grid().occupied()
+}
 
-    const INPUT: &str = "L.LL.LL.LL
+/// the example seat layout of the puzzle, shared by the tests of all submodules
+#[cfg(test)]
+pub(crate) const EXAMPLE: &str = "L.LL.LL.LL
 LLLLLLL.LL
 L.L.L..L..
 LLLL.LL.LL
@@ -183,13 +232,42 @@ LLLLLLLLLL
 L.LLLLLL.L
 L.LLLLL.LL";
 
+#[cfg(test)]
+mod tests {
+    use super::*;
+
     #[test]
     fn part1_example() {
-        assert_eq!(part1(&parse_input_day11(INPUT)), 37);
+        assert_eq!(part1(&parse_input_day11(EXAMPLE)), 37);
     }
 
     #[test]
     fn part2_example() {
-        assert_eq!(part2(&parse_input_day11(INPUT)), 26);
+        assert_eq!(part2(&parse_input_day11(EXAMPLE)), 26);
+    }
+
+    #[test]
+    fn parallel_examples() {
+        let seats = parse_input_day11(EXAMPLE);
+        assert_eq!(part1_parallel(&seats), 37);
+        assert_eq!(part2_parallel(&seats), 26);
+    }
+
+    #[test]
+    fn rounds_until_stable() {
+        let seats = parse_input_day11(EXAMPLE);
+        let mut automaton = adjacent_seating(&seats);
+        automaton.step();
+        assert_eq!(automaton.grid().occupied(), 71);
+        assert_eq!(automaton.run_until_stable(), 4);
+        assert_eq!(automaton.generation(), 6);
+
+        let mut automaton = visible_seating(&seats);
+        assert_eq!(automaton.run_until_stable(), 6);
+        assert_eq!(automaton.grid().occupied(), 26);
+
+        let mut sparse = sparse_visible_seating(&seats);
+        assert_eq!(sparse.run_until_stable(), 6);
+        assert_eq!(sparse.grid(), automaton.grid());
     }
 }
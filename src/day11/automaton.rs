@@ -0,0 +1,392 @@
+use super::{Grid, Seat};
+use core::fmt;
+use std::collections::HashMap;
+use std::fmt::{Display, Formatter};
+use std::mem;
+use std::str::FromStr;
+use std::sync::Arc;
+use std::thread;
+
+/// Decides which seats count as neighbors of a seat.
+/// Has to be `Sync` so rounds can be evaluated on several threads.
+pub trait Neighborhood: Sync {
+    /// number of occupied neighbors of the seat at `index`
+    fn occupied_neighbors(&self, grid: &Grid, index: usize) -> usize;
+}
+
+impl<F: Fn(&Grid, usize) -> usize + Sync> Neighborhood for F {
+    fn occupied_neighbors(&self, grid: &Grid, index: usize) -> usize {
+        self(grid, index)
+    }
+}
+
+pub type RuleFn = dyn Fn(Seat, usize) -> Seat + Send + Sync;
+
+/// How a seat changes depending on its number of occupied neighbors.
+/// Floor never changes, whatever the rule says.
+#[derive(Clone)]
+pub enum Rule {
+    /// B/S notation: an empty seat becomes occupied if its number of occupied neighbors
+    /// is in `birth`, an occupied seat stays occupied if it is in `survival`.
+    /// Both are bit sets over the neighbor counts.
+    BirthSurvival {
+        birth: u32,
+        survival: u32,
+    },
+    Custom(Arc<RuleFn>),
+}
+
+impl Rule {
+    /// `None` if a count does not fit into the bit sets, i.e. is 32 or more.
+    pub fn birth_survival(birth: &[usize], survival: &[usize]) -> Option<Self> {
+        let mask = |counts: &[usize]| {
+            counts.iter().try_fold(0u32, |mask, count| {
+                if *count < 32 {
+                    Some(mask | 1 << count)
+                } else {
+                    None
+                }
+            })
+        };
+        Some(Rule::BirthSurvival {
+            birth: mask(birth)?,
+            survival: mask(survival)?,
+        })
+    }
+
+    pub fn custom(rule: impl Fn(Seat, usize) -> Seat + Send + Sync + 'static) -> Self {
+        Rule::Custom(Arc::new(rule))
+    }
+
+    pub fn apply(&self, seat: Seat, occupied_neighbors: usize) -> Seat {
+        let contains = |mask: u32| occupied_neighbors < 32 && mask & 1 << occupied_neighbors != 0;
+        match (self, seat) {
+            (_, Seat::Floor) => Seat::Floor,
+            (Rule::BirthSurvival { birth, .. }, Seat::Empty) if contains(*birth) => Seat::Occupied,
+            (Rule::BirthSurvival { survival, .. }, Seat::Occupied) if !contains(*survival) => {
+                Seat::Empty
+            }
+            (Rule::BirthSurvival { .. }, _) => seat,
+            (Rule::Custom(rule), _) => rule(seat, occupied_neighbors),
+        }
+    }
+}
+
+/// Parses B/S notation like `B0/S0123`.
+impl FromStr for Rule {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let parse_counts = |s: &str, prefix: char| -> Result<Vec<usize>, ()> {
+            let mut chars = s.chars();
+            if chars.next().map(|c| c.to_ascii_uppercase()) != Some(prefix) {
+                return Err(());
+            }
+            chars
+                .map(|c| c.to_digit(10).map(|d| d as usize).ok_or(()))
+                .collect()
+        };
+        let mut parts = s.trim().split('/');
+        let birth = parse_counts(parts.next().ok_or(())?, 'B')?;
+        let survival = parse_counts(parts.next().ok_or(())?, 'S')?;
+        if parts.next().is_some() {
+            return Err(());
+        }
+        Rule::birth_survival(&birth, &survival).ok_or(())
+    }
+}
+
+impl Display for Rule {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        let write_counts = |f: &mut Formatter<'_>, mask: u32| -> fmt::Result {
+            for count in (0..32).filter(|count| mask & 1 << count != 0) {
+                write!(f, "{}", count)?;
+            }
+            Ok(())
+        };
+        match self {
+            Rule::BirthSurvival { birth, survival } => {
+                f.write_str("B")?;
+                write_counts(f, *birth)?;
+                f.write_str("/S")?;
+                write_counts(f, *survival)
+            }
+            Rule::Custom(_) => f.write_str("custom"),
+        }
+    }
+}
+
+/// A state that was seen before, see [`Automaton::find_cycle`].
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Cycle {
+    /// the first generation that is part of the cycle
+    pub start: usize,
+    /// 1 if the automaton is stable
+    pub period: usize,
+}
+
+/// How the seats of a round are evaluated.
+#[derive(Copy, Clone, Debug, PartialEq, Default)]
+pub enum Execution {
+    #[default]
+    Sequential,
+    /// split the rows into `threads` chunks that are evaluated at the same time
+    Parallel { threads: usize },
+}
+
+impl Execution {
+    /// parallel on all available cores
+    pub fn available_parallelism() -> Self {
+        match thread::available_parallelism() {
+            Ok(threads) if threads.get() > 1 => Execution::Parallel {
+                threads: threads.get(),
+            },
+            _ => Execution::Sequential,
+        }
+    }
+}
+
+/// Evaluates the seats starting at `start` into `next`, returns whether any seat changed.
+fn evaluate(
+    grid: &Grid,
+    neighborhood: &impl Neighborhood,
+    rule: &Rule,
+    start: usize,
+    next: &mut [Seat],
+) -> bool {
+    let mut changed = false;
+    for (index, next) in (start..).zip(next.iter_mut()) {
+        let seat = grid.cells[index];
+        *next = match seat {
+            Seat::Floor => Seat::Floor,
+            _ => rule.apply(seat, neighborhood.occupied_neighbors(grid, index)),
+        };
+        changed |= *next != seat;
+    }
+    changed
+}
+
+/// Applies a rule to every seat at once, writing into a second buffer
+/// that is swapped with the current one after each round.
+pub struct Automaton<N> {
+    grid: Grid,
+    next: Vec<Seat>,
+    neighborhood: N,
+    rule: Rule,
+    execution: Execution,
+    generation: usize,
+}
+
+impl<N: Neighborhood> Automaton<N> {
+    pub fn new(grid: Grid, neighborhood: N, rule: Rule) -> Self {
+        Automaton {
+            next: grid.cells.clone(),
+            grid,
+            neighborhood,
+            rule,
+            execution: Execution::default(),
+            generation: 0,
+        }
+    }
+
+    pub fn set_execution(&mut self, execution: Execution) {
+        self.execution = execution;
+    }
+
+    pub fn grid(&self) -> &Grid {
+        &self.grid
+    }
+
+    /// number of rounds simulated so far
+    pub fn generation(&self) -> usize {
+        self.generation
+    }
+
+    /// Simulates one round, returns whether any seat changed.
+    pub fn step(&mut self) -> bool {
+        let Automaton {
+            grid,
+            next,
+            neighborhood,
+            rule,
+            ..
+        } = self;
+        let changed = match self.execution {
+            Execution::Parallel { threads } if threads > 1 && !next.is_empty() => {
+                // every thread reads the whole previous round and writes its own rows
+                let rows = grid.height.div_ceil(threads);
+                let chunk_size = rows * grid.width;
+                thread::scope(|scope| {
+                    let chunks = next
+                        .chunks_mut(chunk_size)
+                        .enumerate()
+                        .map(|(i, chunk)| {
+                            let (grid, neighborhood, rule) = (&*grid, &*neighborhood, &*rule);
+                            scope.spawn(move || {
+                                evaluate(grid, neighborhood, rule, i * chunk_size, chunk)
+                            })
+                        })
+                        .collect::<Vec<_>>();
+                    chunks
+                        .into_iter()
+                        .fold(false, |changed, chunk| chunk.join().unwrap() | changed)
+                })
+            }
+            _ => evaluate(grid, neighborhood, rule, 0, next),
+        };
+        mem::swap(&mut self.grid.cells, &mut self.next);
+        self.generation += 1;
+        changed
+    }
+
+    /// Simulates exactly `rounds` rounds.
+    pub fn run(&mut self, rounds: usize) {
+        for _ in 0..rounds {
+            self.step();
+        }
+    }
+
+    /// Simulates until a round does not change anything and returns the number of rounds
+    /// that did. Never returns if the automaton cycles, see [`Automaton::find_cycle`].
+    pub fn run_until_stable(&mut self) -> usize {
+        let mut rounds = 0;
+        while self.step() {
+            rounds += 1;
+        }
+        rounds
+    }
+
+    /// Simulates until a state repeats, giving up after `max_rounds` rounds.
+    /// The automaton is left at the end of the first repetition.
+    pub fn find_cycle(&mut self, max_rounds: usize) -> Option<Cycle> {
+        let mut seen = HashMap::new();
+        for _ in 0..=max_rounds {
+            if let Some(start) = seen.insert(self.grid.cells.clone(), self.generation) {
+                return Some(Cycle {
+                    start,
+                    period: self.generation - start,
+                });
+            }
+            self.step();
+        }
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::day11::{Adjacent, LineOfSight};
+    use rand::Rng;
+
+    #[test]
+    fn parse_rules() {
+        let rule = "B0/S0123".parse::<Rule>().unwrap();
+        assert_eq!(rule.to_string(), "B0/S0123");
+        assert_eq!(rule.apply(Seat::Empty, 0), Seat::Occupied);
+        assert_eq!(rule.apply(Seat::Empty, 1), Seat::Empty);
+        assert_eq!(rule.apply(Seat::Occupied, 3), Seat::Occupied);
+        assert_eq!(rule.apply(Seat::Occupied, 4), Seat::Empty);
+        assert_eq!(rule.apply(Seat::Floor, 0), Seat::Floor);
+        assert_eq!("b3/s23".parse::<Rule>().unwrap().to_string(), "B3/S23");
+        assert!("B3".parse::<Rule>().is_err());
+        assert!("B3/S2x".parse::<Rule>().is_err());
+        assert!("S3/B2".parse::<Rule>().is_err());
+
+        assert!(Rule::birth_survival(&[31], &[]).is_some());
+        assert!(Rule::birth_survival(&[0], &[32]).is_none());
+        assert!(Rule::birth_survival(&[usize::MAX], &[]).is_none());
+    }
+
+    #[test]
+    fn closure_rule_matches_notation() {
+        let grid = "L.LL\nLLLL\n#L.L".parse::<Grid>().unwrap();
+        let mut notation = Automaton::new(grid.clone(), Adjacent, "B0/S0123".parse().unwrap());
+        let mut closure = Automaton::new(
+            grid,
+            |grid: &Grid, index| Adjacent.occupied_neighbors(grid, index),
+            Rule::custom(|seat, occupied| match seat {
+                Seat::Empty if occupied == 0 => Seat::Occupied,
+                Seat::Occupied if occupied >= 4 => Seat::Empty,
+                _ => seat,
+            }),
+        );
+        assert_eq!(notation.run_until_stable(), closure.run_until_stable());
+        assert_eq!(notation.grid(), closure.grid());
+        assert_eq!(notation.generation(), closure.generation());
+    }
+
+    #[test]
+    fn cycles() {
+        // every seat flips every round
+        let grid = "L.#\n#.L".parse::<Grid>().unwrap();
+        let blinking = Rule::birth_survival(&[0, 1, 2], &[]).unwrap();
+        let mut automaton = Automaton::new(grid.clone(), Adjacent, blinking.clone());
+        assert_eq!(
+            automaton.find_cycle(10),
+            Some(Cycle {
+                start: 0,
+                period: 2
+            })
+        );
+
+        let mut automaton = Automaton::new(grid.clone(), Adjacent, blinking);
+        automaton.run(3);
+        assert_eq!(automaton.grid().to_string(), "#.L\nL.#\n");
+
+        let mut stable = Automaton::new(grid, Adjacent, "B0/S0123".parse().unwrap());
+        assert_eq!(stable.find_cycle(0), None);
+        assert_eq!(
+            stable.find_cycle(10),
+            Some(Cycle {
+                start: 1,
+                period: 1
+            })
+        );
+    }
+
+    /// Steps both in lockstep for at most 500 rounds, a random grid may oscillate forever.
+    fn compare<N: Neighborhood>(sequential: &mut Automaton<N>, parallel: &mut Automaton<N>) {
+        for _ in 0..500 {
+            let changed = sequential.step();
+            assert_eq!(parallel.step(), changed);
+            assert_eq!(sequential.grid().cells(), parallel.grid().cells());
+            if !changed {
+                break;
+            }
+        }
+    }
+
+    #[test]
+    fn parallel_matches_sequential() {
+        let mut rng = rand::thread_rng();
+        let rows = (0..37)
+            .map(|_| {
+                (0..23)
+                    .map(|_| match rng.gen_range(0, 4) {
+                        0 => Seat::Floor,
+                        1 => Seat::Occupied,
+                        _ => Seat::Empty,
+                    })
+                    .collect()
+            })
+            .collect::<Vec<Vec<Seat>>>();
+        let grid = Grid::new(&rows).unwrap();
+
+        for threads in &[2, 3, 8, 37, 100] {
+            let execution = Execution::Parallel { threads: *threads };
+
+            let rule: Rule = "B0/S0123".parse().unwrap();
+            let mut sequential = Automaton::new(grid.clone(), Adjacent, rule.clone());
+            let mut parallel = Automaton::new(grid.clone(), Adjacent, rule);
+            parallel.set_execution(execution);
+            compare(&mut sequential, &mut parallel);
+
+            let rule: Rule = "B0/S01234".parse().unwrap();
+            let mut sequential = Automaton::new(grid.clone(), LineOfSight, rule.clone());
+            let mut parallel = Automaton::new(grid.clone(), LineOfSight, rule);
+            parallel.set_execution(execution);
+            compare(&mut sequential, &mut parallel);
+        }
+    }
+}
@@ -0,0 +1,240 @@
+use super::automaton::{Automaton, Neighborhood};
+use super::sparse::SparseAutomaton;
+use super::{Grid, Seat};
+use core::fmt;
+use std::fmt::{Display, Formatter};
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::path::{Path, PathBuf};
+use std::thread;
+use std::time::Duration;
+
+/// Anything that simulates the seating round by round.
+pub trait Simulation {
+    fn grid(&self) -> &Grid;
+    /// Simulates one round, returns whether any seat changed.
+    fn step(&mut self) -> bool;
+}
+
+impl<N: Neighborhood> Simulation for Automaton<N> {
+    fn grid(&self) -> &Grid {
+        Automaton::grid(self)
+    }
+
+    fn step(&mut self) -> bool {
+        Automaton::step(self)
+    }
+}
+
+impl Simulation for SparseAutomaton {
+    fn grid(&self) -> &Grid {
+        SparseAutomaton::grid(self)
+    }
+
+    fn step(&mut self) -> bool {
+        SparseAutomaton::step(self)
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct RoundStats {
+    /// 0 is the initial layout
+    pub round: usize,
+    pub occupied: usize,
+    /// seats that changed compared to the previous round
+    pub changed: usize,
+}
+
+impl Display for RoundStats {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "round {}: {} occupied, {} changed",
+            self.round, self.occupied, self.changed
+        )
+    }
+}
+
+/// The layout after every round of a simulation.
+#[derive(Clone, Debug)]
+pub struct Recording {
+    frames: Vec<Grid>,
+}
+
+impl Recording {
+    /// Records the current layout and every round until one does not change anything.
+    pub fn until_stable(simulation: &mut impl Simulation) -> Self {
+        let mut frames = vec![simulation.grid().clone()];
+        while simulation.step() {
+            frames.push(simulation.grid().clone());
+        }
+        Recording { frames }
+    }
+
+    /// Records the current layout and the next `rounds` rounds.
+    pub fn rounds(simulation: &mut impl Simulation, rounds: usize) -> Self {
+        let mut frames = vec![simulation.grid().clone()];
+        for _ in 0..rounds {
+            simulation.step();
+            frames.push(simulation.grid().clone());
+        }
+        Recording { frames }
+    }
+
+    pub fn frames(&self) -> &[Grid] {
+        &self.frames
+    }
+
+    pub fn stats(&self) -> Vec<RoundStats> {
+        self.frames
+            .iter()
+            .enumerate()
+            .map(|(round, frame)| RoundStats {
+                round,
+                occupied: frame.occupied(),
+                changed: match round.checked_sub(1) {
+                    Some(previous) => frame
+                        .cells
+                        .iter()
+                        .zip(&self.frames[previous].cells)
+                        .filter(|(a, b)| a != b)
+                        .count(),
+                    None => 0,
+                },
+            })
+            .collect()
+    }
+
+    /// Writes all rounds one after another, each headed by its statistics.
+    pub fn write_flipbook<W: Write>(&self, out: &mut W) -> io::Result<()> {
+        for (frame, stats) in self.frames.iter().zip(self.stats()) {
+            writeln!(out, "{}", stats)?;
+            writeln!(out, "{}", frame)?;
+        }
+        Ok(())
+    }
+
+    /// Plays the rounds in a terminal, clearing the screen before each one.
+    pub fn replay<W: Write>(&self, out: &mut W, delay: Duration) -> io::Result<()> {
+        for (frame, stats) in self.frames.iter().zip(self.stats()) {
+            write!(out, "\x1b[2J\x1b[H{}\n{}", stats, frame)?;
+            out.flush()?;
+            thread::sleep(delay);
+        }
+        Ok(())
+    }
+
+    /// Writes every round as `round_<n>.ppm` into `directory`, see [`write_ppm`].
+    pub fn save_ppm_frames(&self, directory: &Path, scale: usize) -> io::Result<Vec<PathBuf>> {
+        let digits = self.frames.len().to_string().len();
+        self.frames
+            .iter()
+            .enumerate()
+            .map(|(round, frame)| {
+                let path = directory.join(format!("round_{:0width$}.ppm", round, width = digits));
+                let mut out = BufWriter::new(File::create(&path)?);
+                write_ppm(frame, &mut out, scale)?;
+                out.flush()?;
+                Ok(path)
+            })
+            .collect()
+    }
+}
+
+/// Writes the layout as a binary PPM image with `scale` × `scale` pixels per seat.
+pub fn write_ppm<W: Write>(grid: &Grid, out: &mut W, scale: usize) -> io::Result<()> {
+    write!(
+        out,
+        "P6\n{} {}\n255\n",
+        grid.width * scale,
+        grid.height * scale
+    )?;
+    for row in grid.cells.chunks(grid.width.max(1)) {
+        let line = row
+            .iter()
+            .flat_map(|seat| {
+                let color: [u8; 3] = match seat {
+                    Seat::Floor => [40, 40, 40],
+                    Seat::Empty => [60, 160, 60],
+                    Seat::Occupied => [200, 50, 50],
+                };
+                color.repeat(scale)
+            })
+            .collect::<Vec<u8>>();
+        for _ in 0..scale {
+            out.write_all(&line)?;
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::day11::{adjacent_seating, EXAMPLE};
+
+    #[test]
+    fn record_rounds() {
+        let grid = EXAMPLE.parse::<Grid>().unwrap();
+        let recording = Recording::until_stable(&mut adjacent_seating(&grid));
+        assert_eq!(recording.frames().len(), 6);
+
+        let stats = recording.stats();
+        assert_eq!(
+            stats[0],
+            RoundStats {
+                round: 0,
+                occupied: 0,
+                changed: 0
+            }
+        );
+        assert_eq!(stats[1].occupied, 71);
+        assert_eq!(stats[1].changed, 71);
+        assert_eq!(stats[2].changed, 51);
+        assert_eq!(stats[5].occupied, 37);
+
+        let limited = Recording::rounds(&mut adjacent_seating(&grid), 2);
+        assert_eq!(limited.frames(), &recording.frames()[..3]);
+    }
+
+    #[test]
+    fn flipbook_and_replay() {
+        let grid = "L.L\nL.L".parse::<Grid>().unwrap();
+        let recording = Recording::until_stable(&mut adjacent_seating(&grid));
+        let mut flipbook = Vec::new();
+        recording.write_flipbook(&mut flipbook).unwrap();
+        assert_eq!(
+            String::from_utf8(flipbook).unwrap(),
+            "round 0: 0 occupied, 0 changed
+L.L
+L.L
+
+round 1: 4 occupied, 4 changed
+#.#
+#.#
+
+"
+        );
+
+        let mut replay = Vec::new();
+        recording
+            .replay(&mut replay, Duration::from_millis(0))
+            .unwrap();
+        let replay = String::from_utf8(replay).unwrap();
+        assert_eq!(replay.matches("\x1b[2J").count(), 2);
+        assert!(replay.ends_with("round 1: 4 occupied, 4 changed\n#.#\n#.#\n"));
+    }
+
+    #[test]
+    fn ppm_frame() {
+        let grid = "#.\nLL".parse::<Grid>().unwrap();
+        let mut ppm = Vec::new();
+        write_ppm(&grid, &mut ppm, 2).unwrap();
+        let header = b"P6\n4 4\n255\n";
+        assert_eq!(&ppm[..header.len()], header);
+        assert_eq!(ppm.len(), header.len() + 4 * 4 * 3);
+        // top left pixel is occupied, top right is floor
+        assert_eq!(&ppm[header.len()..header.len() + 3], &[200, 50, 50]);
+        assert_eq!(&ppm[header.len() + 6..header.len() + 9], &[40, 40, 40]);
+    }
+}
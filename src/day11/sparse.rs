@@ -0,0 +1,233 @@
+use super::automaton::{Neighborhood, Rule};
+use super::{Grid, Seat, DIRECTIONS};
+use std::iter::once;
+
+/// The neighbors of every seat, computed once for a specific grid. Floor has no neighbors.
+pub struct NeighborGraph {
+    /// `neighbors[offsets[i]..offsets[i + 1]]` are the neighbors of seat `i`
+    offsets: Vec<usize>,
+    neighbors: Vec<usize>,
+}
+
+impl NeighborGraph {
+    /// Connects every seat to the first seat in each direction, looking across
+    /// at most `range - 1` cells of floor.
+    fn build(grid: &Grid, range: usize) -> Self {
+        let mut offsets = Vec::with_capacity(grid.cells.len() + 1);
+        let mut neighbors = Vec::new();
+        offsets.push(0);
+        for (index, seat) in grid.cells.iter().enumerate() {
+            if *seat != Seat::Floor {
+                let (y, x) = grid.position(index);
+                for (dy, dx) in DIRECTIONS.iter() {
+                    for factor in (1..=range).map(|factor| factor as isize) {
+                        let (y, x) = (y as isize + dy * factor, x as isize + dx * factor);
+                        match grid.get(y, x) {
+                            Some(Seat::Floor) => continue,
+                            Some(_) => neighbors.push(y as usize * grid.width + x as usize),
+                            None => {}
+                        }
+                        break;
+                    }
+                }
+            }
+            offsets.push(neighbors.len());
+        }
+        NeighborGraph { offsets, neighbors }
+    }
+
+    /// the eight seats around each seat
+    pub fn adjacent(grid: &Grid) -> Self {
+        NeighborGraph::build(grid, 1)
+    }
+
+    /// the first seat in each of the eight directions
+    pub fn line_of_sight(grid: &Grid) -> Self {
+        NeighborGraph::build(grid, usize::MAX)
+    }
+
+    pub fn neighbors(&self, index: usize) -> &[usize] {
+        &self.neighbors[self.offsets[index]..self.offsets[index + 1]]
+    }
+}
+
+impl Neighborhood for NeighborGraph {
+    fn occupied_neighbors(&self, grid: &Grid, index: usize) -> usize {
+        self.neighbors(index)
+            .iter()
+            .filter(|neighbor| grid.cells[**neighbor] == Seat::Occupied)
+            .count()
+    }
+}
+
+/// Like [`super::automaton::Automaton`], but only re-evaluates the seats that changed
+/// or have a neighbor that changed in the last round. This relies on the neighbor
+/// graph being symmetric, which both adjacency and line of sight are.
+pub struct SparseAutomaton {
+    grid: Grid,
+    graph: NeighborGraph,
+    rule: Rule,
+    /// seats to evaluate in the next round
+    active: Vec<usize>,
+    queued: Vec<bool>,
+    generation: usize,
+}
+
+impl SparseAutomaton {
+    /// `graph` has to be built from `grid`.
+    pub fn new(grid: Grid, graph: NeighborGraph, rule: Rule) -> Self {
+        let active = (0..grid.cells.len())
+            .filter(|index| grid.cells[*index] != Seat::Floor)
+            .collect();
+        SparseAutomaton {
+            queued: vec![false; grid.cells.len()],
+            grid,
+            graph,
+            rule,
+            active,
+            generation: 0,
+        }
+    }
+
+    pub fn grid(&self) -> &Grid {
+        &self.grid
+    }
+
+    pub fn generation(&self) -> usize {
+        self.generation
+    }
+
+    /// number of seats that will be evaluated in the next round
+    pub fn active_seats(&self) -> usize {
+        self.active.len()
+    }
+
+    /// Simulates one round, returns whether any seat changed.
+    pub fn step(&mut self) -> bool {
+        let changes = self
+            .active
+            .iter()
+            .filter_map(|index| {
+                let seat = self.grid.cells[*index];
+                let occupied = self.graph.occupied_neighbors(&self.grid, *index);
+                let next = self.rule.apply(seat, occupied);
+                if next != seat {
+                    Some((*index, next))
+                } else {
+                    None
+                }
+            })
+            .collect::<Vec<(usize, Seat)>>();
+
+        self.active.clear();
+        for (index, seat) in &changes {
+            self.grid.cells[*index] = *seat;
+            for affected in once(index).chain(self.graph.neighbors(*index)) {
+                if !self.queued[*affected] {
+                    self.queued[*affected] = true;
+                    self.active.push(*affected);
+                }
+            }
+        }
+        for index in &self.active {
+            self.queued[*index] = false;
+        }
+        self.generation += 1;
+        !changes.is_empty()
+    }
+
+    /// Simulates exactly `rounds` rounds.
+    pub fn run(&mut self, rounds: usize) {
+        for _ in 0..rounds {
+            self.step();
+        }
+    }
+
+    /// Simulates until a round does not change anything and returns the number of rounds that did.
+    pub fn run_until_stable(&mut self) -> usize {
+        let mut rounds = 0;
+        while self.step() {
+            rounds += 1;
+        }
+        rounds
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::day11::automaton::Automaton;
+    use crate::day11::{Adjacent, LineOfSight};
+    use rand::Rng;
+
+    fn random_grid(height: usize, width: usize) -> Grid {
+        let mut rng = rand::thread_rng();
+        let rows = (0..height)
+            .map(|_| {
+                (0..width)
+                    .map(|_| match rng.gen_range(0, 3) {
+                        0 => Seat::Floor,
+                        _ => Seat::Empty,
+                    })
+                    .collect()
+            })
+            .collect::<Vec<Vec<Seat>>>();
+        Grid::new(&rows).unwrap()
+    }
+
+    #[test]
+    fn neighbor_graph() {
+        let grid = "L.L.L\n.....\nL.#.L".parse::<Grid>().unwrap();
+        assert_eq!(NeighborGraph::adjacent(&grid).neighbors(0), &[] as &[usize]);
+        let graph = NeighborGraph::line_of_sight(&grid);
+        assert_eq!(graph.neighbors(0), &[2, 10, 12]);
+        assert_eq!(graph.neighbors(1), &[] as &[usize]);
+        assert_eq!(graph.occupied_neighbors(&grid, 0), 1);
+        assert_eq!(graph.occupied_neighbors(&grid, 2), 1);
+        assert_eq!(graph.occupied_neighbors(&grid, 12), 0);
+    }
+
+    /// Steps both automata until they are stable, random grids are not guaranteed to
+    /// become stable so this gives up after `max_rounds`.
+    fn compare<N: Neighborhood>(
+        dense: &mut Automaton<N>,
+        sparse: &mut SparseAutomaton,
+        max_rounds: usize,
+    ) {
+        for _ in 0..max_rounds {
+            let changed = dense.step();
+            assert_eq!(changed, sparse.step());
+            assert_eq!(dense.grid(), sparse.grid());
+            if !changed {
+                assert_eq!(sparse.active_seats(), 0);
+                break;
+            }
+        }
+        assert_eq!(dense.generation(), sparse.generation());
+    }
+
+    #[test]
+    fn matches_dense_automaton() {
+        let grid = random_grid(60, 80);
+        let adjacent = Rule::birth_survival(&[0], &[0, 1, 2, 3]).unwrap();
+        let visible = Rule::birth_survival(&[0], &[0, 1, 2, 3, 4]).unwrap();
+
+        let mut dense = Automaton::new(grid.clone(), Adjacent, adjacent.clone());
+        let mut sparse =
+            SparseAutomaton::new(grid.clone(), NeighborGraph::adjacent(&grid), adjacent);
+        compare(&mut dense, &mut sparse, 500);
+
+        let mut dense = Automaton::new(grid.clone(), LineOfSight, visible.clone());
+        let mut sparse =
+            SparseAutomaton::new(grid.clone(), NeighborGraph::line_of_sight(&grid), visible);
+        compare(&mut dense, &mut sparse, 500);
+
+        // the example becomes stable
+        let grid = crate::day11::EXAMPLE.parse::<Grid>().unwrap();
+        let rule = Rule::birth_survival(&[0], &[0, 1, 2, 3]).unwrap();
+        let mut dense = Automaton::new(grid.clone(), Adjacent, rule.clone());
+        let mut sparse = SparseAutomaton::new(grid.clone(), NeighborGraph::adjacent(&grid), rule);
+        assert_eq!(dense.run_until_stable(), sparse.run_until_stable());
+        assert_eq!(sparse.active_seats(), 0);
+    }
+}
@@ -2,87 +2,103 @@ use aoc_runner_derive::{aoc, aoc_generator};
 use std::str::FromStr;
 use std::fmt::{Display, Formatter, Write};
 use core::fmt;
-use std::mem::swap;
+use navigator::{Navigator, Ship, WaypointShip};
+use vector::{RotationError, Scalar, Vector};
 
-enum Direction {
+pub mod navigator;
+pub mod planner;
+pub mod trajectory;
+pub mod vector;
+
+/// A compass direction.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum Heading {
     North,
     South,
     East,
     West,
+}
+
+impl Display for Heading {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            Heading::North => f.write_char('N'),
+            Heading::South => f.write_char('S'),
+            Heading::East => f.write_char('E'),
+            Heading::West => f.write_char('W'),
+        }
+    }
+}
+
+impl Heading {
+    /// unit vector pointing in this direction
+    pub fn vector<T: Scalar>(self) -> Vector<T> {
+        let (one, zero) = (T::from_amount(1), T::default());
+        match self {
+            Heading::North => Vector::new(zero, one),
+            Heading::South => Vector::new(zero, -one),
+            Heading::East => Vector::new(one, zero),
+            Heading::West => Vector::new(-one, zero),
+        }
+    }
+}
+
+/// What an action tells the navigator to do, see [`Navigator`] for the meaning.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum Command {
+    Move(Heading),
     Left,
     Right,
     Forward,
 }
 
-impl Display for Direction {
+impl Display for Command {
     fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
         match self {
-            Direction::North => f.write_char('N')?,
-            Direction::South => f.write_char('S')?,
-            Direction::East => f.write_char('E')?,
-            Direction::West => f.write_char('W')?,
-            Direction::Left => f.write_char('L')?,
-            Direction::Right => f.write_char('R')?,
-            Direction::Forward => f.write_char('F')?,
+            Command::Move(heading) => heading.fmt(f),
+            Command::Left => f.write_char('L'),
+            Command::Right => f.write_char('R'),
+            Command::Forward => f.write_char('F'),
         }
-        Ok(())
     }
 }
 
-impl Direction {
+impl Command {
     fn from_char(c: char) -> Result<Self, ()> {
         Ok(match c {
-            'N' => Direction::North,
-            'S' => Direction::South,
-            'E' => Direction::East,
-            'W' => Direction::West,
-            'L' => Direction::Left,
-            'R' => Direction::Right,
-            'F' => Direction::Forward,
+            'N' => Command::Move(Heading::North),
+            'S' => Command::Move(Heading::South),
+            'E' => Command::Move(Heading::East),
+            'W' => Command::Move(Heading::West),
+            'L' => Command::Left,
+            'R' => Command::Right,
+            'F' => Command::Forward,
             _ => return Err(()),
         })
     }
-
-    fn rot_left(&self) -> Self {
-        match self {
-            Direction::North => Direction::West,
-            Direction::South => Direction::East,
-            Direction::East => Direction::North,
-            Direction::West => Direction::South,
-            _ => unreachable!(),
-        }
-    }
-
-    fn rot_right(&self) -> Self {
-        match self {
-            Direction::North => Direction::East,
-            Direction::South => Direction::West,
-            Direction::East => Direction::South,
-            Direction::West => Direction::North,
-            _ => unreachable!(),
-        }
-    }
 }
 
-struct Action {
-    direction: Direction,
-    amount: usize,
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub struct Action {
+    pub command: Command,
+    pub amount: usize,
 }
 
 impl FromStr for Action {
     type Err = ();
 
     fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut chars = s.chars();
         Ok(Action {
-            direction: Direction::from_char(s.chars().next().map_or(Err(()), Ok)?)?,
-            amount: s[1..].parse().map_err(|_| ())?,
+            command: Command::from_char(chars.next().ok_or(())?)?,
+            amount: chars.as_str().parse().map_err(|_| ())?,
         })
     }
 }
 
 impl Display for Action {
     fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
-        self.direction.fmt(f)?;
+        self.command.fmt(f)?;
         std::fmt::Display::fmt(&self.amount, f)?;
         Ok(())
     }
@@ -92,133 +108,69 @@ impl Display for Action {
 fn parse_input_day12(input: &str) -> Vec<Action> {
     input
         .lines()
-        .filter_map(|line| Action::from_str(line).map_or(None, Some))
+        .filter_map(|line| Action::from_str(line).ok())
         .collect()
 }
 
 #[aoc(day12, part1)]
-fn part1(input: &Vec<Action>) -> usize {
-    let mut direction = Direction::East;
-    let mut position = (0isize, 0isize);
-
-    for action in input {
-        let amount = action.amount as isize;
-        let action_direction = match action.direction {
-            Direction::Forward => &direction,
-            _ => &action.direction,
-        };
-        match action_direction {
-            Direction::North => position.0 += amount,
-            Direction::South => position.0 -= amount,
-            Direction::East => position.1 += amount,
-            Direction::West => position.1 -= amount,
-            Direction::Left => {
-                let turns = amount / 90;
-                for _ in 0..turns {
-                    direction = direction.rot_left();
-                }
-            },
-            Direction::Right => {
-                let turns = amount / 90;
-                for _ in 0..turns {
-                    direction = direction.rot_right();
-                }
-            },
-            _ => unreachable!(),
-        }
-    }
-
-    (position.0.abs() + position.1.abs()) as usize
+fn part1(input: &[Action]) -> Result<usize, RotationError> {
+    Ok(Ship::<isize>::new().navigate(input)?.manhattan())
 }
 
-type DirectionalAmount = (isize, Direction);
-type Waypoint = (DirectionalAmount, DirectionalAmount);
-
 #[aoc(day12, part2)]
-fn part2(input: &Vec<Action>) -> usize {
-    let mut position = (0isize, 0isize);
-    let mut waypoint: Waypoint = (
-        (10isize, Direction::East),
-        (1isize, Direction::North),
-    );
-
-    for action in input {
-        let amount = action.amount as isize;
-        match action.direction {
-            Direction::North => waypoint.1.0 += match waypoint.1.1 {
-                Direction::North => amount,
-                Direction::South => -amount,
-                _ => unreachable!(),
-            },
-            Direction::South => waypoint.1.0 += match waypoint.1.1 {
-                Direction::South => amount,
-                Direction::North => -amount,
-                _ => unreachable!(),
-            },
-            Direction::East => waypoint.0.0 += match waypoint.0.1 {
-                Direction::East => amount,
-                Direction::West => -amount,
-                _ => unreachable!(),
-            },
-            Direction::West => waypoint.0.0 += match waypoint.0.1 {
-                Direction::West => amount,
-                Direction::East => -amount,
-                _ => unreachable!(),
-            },
-            Direction::Left => {
-                let turns = amount / 90;
-                for _ in 0..turns {
-                    swap(&mut waypoint.0.0, &mut waypoint.1.0);
-                    swap(&mut waypoint.0.1, &mut waypoint.1.1);
-                    waypoint.0.1 = waypoint.0.1.rot_left();
-                    waypoint.1.1 = waypoint.1.1.rot_left();
-                }
-            },
-            Direction::Right => {
-                let turns = amount / 90;
-                for _ in 0..turns {
-                    swap(&mut waypoint.0.0, &mut waypoint.1.0);
-                    swap(&mut waypoint.0.1, &mut waypoint.1.1);
-                    waypoint.0.1 = waypoint.0.1.rot_right();
-                    waypoint.1.1 = waypoint.1.1.rot_right();
-                }
-            },
-            Direction::Forward => {
-                position.0 += match waypoint.0.1 {
-                    Direction::East => amount * waypoint.0.0,
-                    Direction::West => amount * -waypoint.0.0,
-                    _ => unreachable!(),
-                };
-                position.1 += match waypoint.1.1 {
-                    Direction::North => amount * waypoint.1.0,
-                    Direction::South => amount * -waypoint.1.0,
-                    _ => unreachable!(),
-                };
-            }
-        }
-        //println!("action={}\tway=({} {}, {} {})\tpos=({}, {})", action, waypoint.0.0, waypoint.0.1, waypoint.1.0, waypoint.1.1, position.0, position.1);
-    }
-
-    (position.0.abs() + position.1.abs()) as usize
+fn part2(input: &[Action]) -> Result<usize, RotationError> {
+    Ok(WaypointShip::<isize>::new().navigate(input)?.manhattan())
 }
 
+/// the example course of the puzzle, shared by the tests of all submodules
 #[cfg(test)]
-mod tests {
-    use super::*;
-
-    const INPUT: &str = "F10
+pub(crate) const EXAMPLE: &str = "F10
 N3
 F7
 R90
 F11";
 
+#[cfg(test)]
+mod tests {
+    use super::*;
+
     #[test]
     fn part1_example() {
-        assert_eq!(part1(&parse_input_day12(INPUT)), 25);
+        assert_eq!(part1(&parse_input_day12(EXAMPLE)), Ok(25));
     }
 
     #[test]
     fn part2_example() {
-        assert_eq!(part2(&parse_input_day12(INPUT)), 286);
+        assert_eq!(part2(&parse_input_day12(EXAMPLE)), Ok(286));
+    }
+
+    #[test]
+    fn parse_actions() {
+        let action = "W15".parse::<Action>().unwrap();
+        assert_eq!(action.command, Command::Move(Heading::West));
+        assert_eq!(action.amount, 15);
+        assert_eq!(action.to_string(), "W15");
+        assert!("X1".parse::<Action>().is_err());
+        assert!("F".parse::<Action>().is_err());
+        assert!("".parse::<Action>().is_err());
+    }
+
+    #[test]
+    fn unsupported_angles() {
+        let actions = parse_input_day12("F10\nR45\nF10");
+        assert_eq!(part1(&actions), Err(RotationError::UnsupportedAngle(-45)));
+        assert_eq!(
+            part2(&parse_input_day12("L100")),
+            Err(RotationError::UnsupportedAngle(100))
+        );
+
+        let position = Ship::<f64>::new().navigate(&actions).unwrap();
+        assert!((position.east - (10.0 + 50f64.sqrt())).abs() < 1e-9);
+        assert!((position.north + 50f64.sqrt()).abs() < 1e-9);
+
+        // odd angles adding up to quarter turns end up close to the integer result
+        let actions = parse_input_day12("R30\nR60\nF10\nL200\nL160\nF3");
+        let position = Ship::<f64>::new().navigate(&actions).unwrap();
+        assert!((position - Vector::new(0.0, -13.0)).manhattan() < 1e-9);
     }
 }
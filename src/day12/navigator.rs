@@ -0,0 +1,239 @@
+use super::vector::{RotationError, Scalar, Vector};
+use super::{Action, Command, Heading};
+
+/// Interprets actions, every navigation model decides on its own what they mean.
+pub trait Navigator {
+    type Coordinate: Scalar;
+
+    /// position of the ship
+    fn position(&self) -> Vector<Self::Coordinate>;
+
+    /// position of the waypoint relative to the ship, if the model has one
+    fn waypoint(&self) -> Option<Vector<Self::Coordinate>> {
+        None
+    }
+
+    /// `N`, `S`, `E` and `W`
+    fn shift(&mut self, heading: Heading, amount: Self::Coordinate);
+
+    /// `L` and `R`, counterclockwise by `degrees` or clockwise if negative
+    fn turn(&mut self, degrees: isize) -> Result<(), RotationError>;
+
+    /// `F`
+    fn forward(&mut self, amount: Self::Coordinate);
+
+    fn apply(&mut self, action: &Action) -> Result<(), RotationError> {
+        let amount = Self::Coordinate::from_amount(action.amount);
+        match action.command {
+            Command::Move(heading) => self.shift(heading, amount),
+            Command::Left => self.turn(action.amount as isize)?,
+            Command::Right => self.turn(-(action.amount as isize))?,
+            Command::Forward => self.forward(amount),
+        }
+        Ok(())
+    }
+
+    /// Applies all actions and returns the final position.
+    fn navigate(&mut self, actions: &[Action]) -> Result<Vector<Self::Coordinate>, RotationError> {
+        for action in actions {
+            self.apply(action)?;
+        }
+        Ok(self.position())
+    }
+}
+
+/// Moves in the compass directions or the direction it is facing and turns itself (part 1).
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Ship<T> {
+    pub position: Vector<T>,
+    /// unit vector the ship is facing
+    pub heading: Vector<T>,
+}
+
+impl<T: Scalar> Ship<T> {
+    /// starts at the origin facing east
+    pub fn new() -> Self {
+        Ship::facing(Heading::East)
+    }
+
+    pub fn facing(heading: Heading) -> Self {
+        Ship {
+            position: Vector::default(),
+            heading: heading.vector(),
+        }
+    }
+}
+
+impl<T: Scalar> Default for Ship<T> {
+    fn default() -> Self {
+        Ship::new()
+    }
+}
+
+impl<T: Scalar> Navigator for Ship<T> {
+    type Coordinate = T;
+
+    fn position(&self) -> Vector<T> {
+        self.position
+    }
+
+    fn shift(&mut self, heading: Heading, amount: T) {
+        self.position += heading.vector() * amount;
+    }
+
+    fn turn(&mut self, degrees: isize) -> Result<(), RotationError> {
+        self.heading = self.heading.rotate(degrees)?;
+        Ok(())
+    }
+
+    fn forward(&mut self, amount: T) {
+        self.position += self.heading * amount;
+    }
+}
+
+/// Moves and turns a waypoint relative to the ship, the ship only moves towards it (part 2).
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct WaypointShip<T> {
+    pub position: Vector<T>,
+    /// relative to the ship
+    pub waypoint: Vector<T>,
+}
+
+impl<T: Scalar> WaypointShip<T> {
+    /// starts at the origin with the waypoint 10 east and 1 north
+    pub fn new() -> Self {
+        WaypointShip::with_waypoint(Vector::new(T::from_amount(10), T::from_amount(1)))
+    }
+
+    pub fn with_waypoint(waypoint: Vector<T>) -> Self {
+        WaypointShip {
+            position: Vector::default(),
+            waypoint,
+        }
+    }
+}
+
+impl<T: Scalar> Default for WaypointShip<T> {
+    fn default() -> Self {
+        WaypointShip::new()
+    }
+}
+
+impl<T: Scalar> Navigator for WaypointShip<T> {
+    type Coordinate = T;
+
+    fn position(&self) -> Vector<T> {
+        self.position
+    }
+
+    fn waypoint(&self) -> Option<Vector<T>> {
+        Some(self.waypoint)
+    }
+
+    fn shift(&mut self, heading: Heading, amount: T) {
+        self.waypoint += heading.vector() * amount;
+    }
+
+    fn turn(&mut self, degrees: isize) -> Result<(), RotationError> {
+        self.waypoint = self.waypoint.rotate(degrees)?;
+        Ok(())
+    }
+
+    fn forward(&mut self, amount: T) {
+        self.position += self.waypoint * amount;
+    }
+}
+
+/// Any other navigator, with a current that moves the ship after every action.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Drift<N: Navigator> {
+    pub navigator: N,
+    pub current: Vector<N::Coordinate>,
+    /// how far the current has moved the ship so far
+    pub drifted: Vector<N::Coordinate>,
+}
+
+impl<N: Navigator> Drift<N> {
+    pub fn new(navigator: N, current: Vector<N::Coordinate>) -> Self {
+        Drift {
+            navigator,
+            current,
+            drifted: Vector::default(),
+        }
+    }
+}
+
+impl<N: Navigator> Navigator for Drift<N> {
+    type Coordinate = N::Coordinate;
+
+    fn position(&self) -> Vector<N::Coordinate> {
+        self.navigator.position() + self.drifted
+    }
+
+    fn waypoint(&self) -> Option<Vector<N::Coordinate>> {
+        self.navigator.waypoint()
+    }
+
+    fn shift(&mut self, heading: Heading, amount: N::Coordinate) {
+        self.navigator.shift(heading, amount);
+    }
+
+    fn turn(&mut self, degrees: isize) -> Result<(), RotationError> {
+        self.navigator.turn(degrees)
+    }
+
+    fn forward(&mut self, amount: N::Coordinate) {
+        self.navigator.forward(amount);
+    }
+
+    fn apply(&mut self, action: &Action) -> Result<(), RotationError> {
+        self.navigator.apply(action)?;
+        self.drifted += self.current;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::day12::{parse_input_day12, EXAMPLE};
+
+    #[test]
+    fn ship_and_waypoint() {
+        let mut ship = Ship::<isize>::new();
+        assert_eq!(
+            ship.navigate(&parse_input_day12(EXAMPLE)),
+            Ok(Vector::new(17, -8))
+        );
+        assert_eq!(ship.heading, Heading::South.vector());
+
+        let mut ship = Ship::<isize>::facing(Heading::North);
+        assert_eq!(
+            ship.navigate(&parse_input_day12("F2\nL90\nF3")),
+            Ok(Vector::new(-3, 2))
+        );
+
+        let mut ship = WaypointShip::<isize>::new();
+        assert_eq!(
+            ship.navigate(&parse_input_day12(EXAMPLE)),
+            Ok(Vector::new(214, -72))
+        );
+        assert_eq!(ship.waypoint, Vector::new(4, -10));
+    }
+
+    #[test]
+    fn drift() {
+        // one east and one south after each of the five actions
+        let mut ship = Drift::new(Ship::<isize>::new(), Vector::new(1, -1));
+        assert_eq!(
+            ship.navigate(&parse_input_day12(EXAMPLE)),
+            Ok(Vector::new(22, -13))
+        );
+
+        let mut ship = Drift::new(WaypointShip::<f64>::new(), Vector::new(0.0, 0.5));
+        assert_eq!(
+            ship.navigate(&parse_input_day12(EXAMPLE)),
+            Ok(Vector::new(214.0, -69.5))
+        );
+    }
+}
@@ -0,0 +1,260 @@
+use super::navigator::{Navigator, Ship, WaypointShip};
+use super::vector::Vector;
+use super::{Action, Command, Heading};
+
+/// Compass moves covering `delta`, east/west first, skipping axes without distance.
+fn axis_moves(delta: Vector<isize>) -> Vec<(Heading, usize)> {
+    let east = match delta.east {
+        e if e > 0 => Some((Heading::East, e as usize)),
+        e if e < 0 => Some((Heading::West, e.unsigned_abs())),
+        _ => None,
+    };
+    let north = match delta.north {
+        n if n > 0 => Some((Heading::North, n as usize)),
+        n if n < 0 => Some((Heading::South, n.unsigned_abs())),
+        _ => None,
+    };
+    east.into_iter().chain(north).collect()
+}
+
+fn shift_actions(delta: Vector<isize>) -> impl Iterator<Item = Action> {
+    axis_moves(delta)
+        .into_iter()
+        .map(|(heading, amount)| Action {
+            command: Command::Move(heading),
+            amount,
+        })
+}
+
+fn forward(amount: usize) -> Action {
+    Action {
+        command: Command::Forward,
+        amount,
+    }
+}
+
+/// the `k >= 1` with `k * v == target`
+fn multiple(target: Vector<isize>, v: Vector<isize>) -> Option<usize> {
+    let k = match (v.east, v.north) {
+        (0, 0) => return None,
+        (0, n) => target.north / n,
+        (e, _) => target.east / e,
+    };
+    if k >= 1 && v * k == target {
+        Some(k as usize)
+    } else {
+        None
+    }
+}
+
+/// Whether `actions` take the navigator exactly to `target`.
+pub fn reaches<N: Navigator<Coordinate = isize>>(
+    mut navigator: N,
+    actions: &[Action],
+    target: Vector<isize>,
+) -> bool {
+    navigator.navigate(actions) == Ok(target)
+}
+
+/// Shortest route to `target` for a [`Ship`] starting at the origin facing `heading`.
+/// Every action moves along one axis, so it takes one action per axis that has a distance;
+/// `F` is used where the ship already faces the right way. The route is replayed before
+/// it is returned.
+pub fn plan_ship(target: Vector<isize>, heading: Heading) -> Vec<Action> {
+    let route = axis_moves(target)
+        .into_iter()
+        .map(|(direction, amount)| Action {
+            command: if direction == heading {
+                Command::Forward
+            } else {
+                Command::Move(direction)
+            },
+            amount,
+        })
+        .collect::<Vec<Action>>();
+    assert!(
+        reaches(Ship::facing(heading), &route, target),
+        "planned route misses the target"
+    );
+    route
+}
+
+/// Shortest route to `target` for a [`WaypointShip`] starting at the origin with `waypoint`.
+///
+/// Only `F` moves the ship, so the route ends with one. Before it, the waypoint has to be
+/// turned or moved so that the target is a multiple of it. Moving it onto the target
+/// always works, which takes at most three actions in total. The route is replayed before
+/// it is returned.
+pub fn plan_waypoint(target: Vector<isize>, waypoint: Vector<isize>) -> Vec<Action> {
+    let route = plan_waypoint_unchecked(target, waypoint);
+    assert!(
+        reaches(WaypointShip::with_waypoint(waypoint), &route, target),
+        "planned route misses the target"
+    );
+    route
+}
+
+fn plan_waypoint_unchecked(target: Vector<isize>, waypoint: Vector<isize>) -> Vec<Action> {
+    if target == Vector::default() {
+        return Vec::new();
+    }
+    if let Some(k) = multiple(target, waypoint) {
+        return vec![forward(k)];
+    }
+
+    // turn the waypoint
+    for (command, degrees) in [
+        (Command::Left, 90),
+        (Command::Left, 180),
+        (Command::Right, 90),
+    ] {
+        let turned = waypoint.rotate(if command == Command::Left {
+            degrees
+        } else {
+            -degrees
+        });
+        if let Some(k) = turned.ok().and_then(|turned| multiple(target, turned)) {
+            return vec![
+                Action {
+                    command,
+                    amount: degrees as usize,
+                },
+                forward(k),
+            ];
+        }
+    }
+
+    // move the waypoint along one axis, keeping the other component
+    let keep_east = match (waypoint.east, target.east) {
+        (0, 0) => Some(1),
+        (0, _) => None,
+        (w, t) if t % w == 0 && t / w >= 1 => Some(t / w),
+        _ => None,
+    }
+    .filter(|k| target.north % k == 0)
+    .map(|k| (k, Vector::new(waypoint.east, target.north / k)));
+    let keep_north = match (waypoint.north, target.north) {
+        (0, 0) => Some(1),
+        (0, _) => None,
+        (w, t) if t % w == 0 && t / w >= 1 => Some(t / w),
+        _ => None,
+    }
+    .filter(|k| target.east % k == 0)
+    .map(|k| (k, Vector::new(target.east / k, waypoint.north)));
+    if let Some((k, moved)) = keep_east.or(keep_north) {
+        return shift_actions(moved - waypoint)
+            .chain(Some(forward(k as usize)))
+            .collect();
+    }
+
+    // move the waypoint onto the target
+    shift_actions(target - waypoint)
+        .chain(Some(forward(1)))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+
+    fn route(actions: &[Action]) -> String {
+        actions
+            .iter()
+            .map(|action| action.to_string())
+            .collect::<Vec<String>>()
+            .join(" ")
+    }
+
+    #[test]
+    fn ship_routes() {
+        assert_eq!(
+            route(&plan_ship(Vector::new(17, -8), Heading::East)),
+            "F17 S8"
+        );
+        assert_eq!(route(&plan_ship(Vector::new(-3, 0), Heading::East)), "W3");
+        assert_eq!(route(&plan_ship(Vector::new(0, 5), Heading::North)), "F5");
+        assert!(plan_ship(Vector::default(), Heading::South).is_empty());
+
+        for east in -5..=5 {
+            for north in -5..=5 {
+                let target = Vector::new(east, north);
+                let actions = plan_ship(target, Heading::West);
+                assert!(reaches(Ship::facing(Heading::West), &actions, target));
+                assert_eq!(actions.len(), (east != 0) as usize + (north != 0) as usize);
+            }
+        }
+    }
+
+    #[test]
+    fn waypoint_routes() {
+        let start = Vector::new(10, 1);
+        assert_eq!(route(&plan_waypoint(Vector::new(30, 3), start)), "F3");
+        assert_eq!(route(&plan_waypoint(Vector::new(-2, 20), start)), "L90 F2");
+        assert_eq!(route(&plan_waypoint(Vector::new(20, 6), start)), "N2 F2");
+        assert_eq!(route(&plan_waypoint(Vector::new(7, -7), start)), "W3 S8 F1");
+    }
+
+    /// all positions reachable with at most two actions, with the fewest actions needed
+    fn reachable_within_two(waypoint: Vector<isize>) -> HashMap<Vector<isize>, usize> {
+        let mut actions = vec![];
+        for amount in 1..=25 {
+            for heading in [Heading::North, Heading::South, Heading::East, Heading::West] {
+                actions.push(Action {
+                    command: Command::Move(heading),
+                    amount,
+                });
+            }
+            actions.push(forward(amount));
+        }
+        for (command, amount) in [
+            (Command::Left, 90),
+            (Command::Left, 180),
+            (Command::Right, 90),
+        ] {
+            actions.push(Action { command, amount });
+        }
+
+        let mut reachable = HashMap::new();
+        let mut reach = |position, length: usize| {
+            let fewest = reachable.entry(position).or_insert(length);
+            *fewest = length.min(*fewest);
+        };
+        reach(Vector::default(), 0);
+        for first in &actions {
+            let mut ship = WaypointShip::with_waypoint(waypoint);
+            reach(ship.navigate(&[*first]).unwrap(), 1);
+            for second in &actions {
+                let mut ship = ship;
+                reach(ship.navigate(&[*second]).unwrap(), 2);
+            }
+        }
+        reachable
+    }
+
+    #[test]
+    fn waypoint_routes_are_minimal() {
+        for waypoint in [Vector::new(10, 1), Vector::new(0, 3), Vector::new(-2, -2)] {
+            let reachable = reachable_within_two(waypoint);
+            for east in -10..=10 {
+                for north in -10..=10 {
+                    let target = Vector::new(east, north);
+                    let actions = plan_waypoint(target, waypoint);
+                    assert!(reaches(
+                        WaypointShip::with_waypoint(waypoint),
+                        &actions,
+                        target
+                    ));
+                    assert_eq!(
+                        actions.len(),
+                        reachable.get(&target).copied().unwrap_or(3),
+                        "{:?} with waypoint {:?}: {}",
+                        target,
+                        waypoint,
+                        route(&actions)
+                    );
+                }
+            }
+        }
+    }
+}
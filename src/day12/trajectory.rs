@@ -0,0 +1,252 @@
+use super::navigator::Navigator;
+use super::vector::{RotationError, Scalar, Vector};
+use super::Action;
+use std::fmt::Display;
+use std::io::{self, Write};
+
+/// Where the ship was after an action.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct TrackPoint<T> {
+    /// `None` for the starting point
+    pub action: Option<Action>,
+    pub position: Vector<T>,
+    /// relative to the ship, if the navigator has one
+    pub waypoint: Option<Vector<T>>,
+}
+
+/// The full path of a voyage.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Trajectory<T> {
+    points: Vec<TrackPoint<T>>,
+}
+
+impl<T: Scalar> Trajectory<T> {
+    /// Applies all actions, recording the state of the navigator before the first
+    /// and after every action.
+    pub fn record<N: Navigator<Coordinate = T>>(
+        navigator: &mut N,
+        actions: &[Action],
+    ) -> Result<Self, RotationError> {
+        let mut points = vec![TrackPoint {
+            action: None,
+            position: navigator.position(),
+            waypoint: navigator.waypoint(),
+        }];
+        for action in actions {
+            navigator.apply(action)?;
+            points.push(TrackPoint {
+                action: Some(*action),
+                position: navigator.position(),
+                waypoint: navigator.waypoint(),
+            });
+        }
+        Ok(Trajectory { points })
+    }
+
+    pub fn points(&self) -> &[TrackPoint<T>] {
+        &self.points
+    }
+
+    /// absolute positions of the waypoint, empty if the navigator does not have one
+    fn waypoint_path(&self) -> Vec<Vector<T>> {
+        self.points
+            .iter()
+            .filter_map(|point| Some(point.position + point.waypoint?))
+            .collect()
+    }
+
+    /// Writes one line per point, the waypoint columns stay empty without waypoint.
+    pub fn write_csv<W: Write>(&self, out: &mut W) -> io::Result<()>
+    where
+        T: Display,
+    {
+        writeln!(out, "step,action,east,north,waypoint_east,waypoint_north")?;
+        for (step, point) in self.points.iter().enumerate() {
+            write!(out, "{},", step)?;
+            if let Some(action) = point.action {
+                write!(out, "{}", action)?;
+            }
+            write!(out, ",{},{},", point.position.east, point.position.north)?;
+            if let Some(waypoint) = point.waypoint {
+                write!(out, "{},{}", waypoint.east, waypoint.north)?;
+            } else {
+                write!(out, ",")?;
+            }
+            writeln!(out)?;
+        }
+        Ok(())
+    }
+
+    /// Draws the path of the ship (and the waypoint) scaled to fit into `size` × `size` pixels,
+    /// with north up. Every point can be hovered to show the action that led there.
+    pub fn write_svg<W: Write>(&self, out: &mut W, size: f64) -> io::Result<()> {
+        const MARGIN: f64 = 40.0;
+
+        let waypoints = self.waypoint_path();
+        let all = self
+            .points
+            .iter()
+            .map(|point| point.position)
+            .chain(waypoints.iter().copied());
+        let (mut min, mut max) = (Vector::<f64>::default(), Vector::<f64>::default());
+        for v in all {
+            let (east, north) = (v.east.to_f64(), v.north.to_f64());
+            min = Vector::new(min.east.min(east), min.north.min(north));
+            max = Vector::new(max.east.max(east), max.north.max(north));
+        }
+        let span = (max - min).east.max((max - min).north).max(1.0);
+        let scale = (size - 2.0 * MARGIN).max(1.0) / span;
+        let width = (max.east - min.east) * scale + 2.0 * MARGIN;
+        let height = (max.north - min.north) * scale + 2.0 * MARGIN;
+        let x = |east: f64| MARGIN + (east - min.east) * scale;
+        let y = |north: f64| MARGIN + (max.north - north) * scale;
+        let pixel = |v: Vector<T>| (x(v.east.to_f64()), y(v.north.to_f64()));
+        let polyline = |path: &mut dyn Iterator<Item = Vector<T>>| {
+            path.map(|v| format!("{:.1},{:.1}", pixel(v).0, pixel(v).1))
+                .collect::<Vec<String>>()
+                .join(" ")
+        };
+
+        writeln!(
+            out,
+            r#"<svg xmlns="http://www.w3.org/2000/svg" width="{:.0}" height="{:.0}" viewBox="0 0 {:.1} {:.1}">"#,
+            width, height, width, height
+        )?;
+
+        // axes through the origin, labelled with the covered range
+        writeln!(
+            out,
+            r#"  <g stroke="gray" stroke-width="1"><line x1="{:.1}" y1="{:.1}" x2="{:.1}" y2="{:.1}"/><line x1="{:.1}" y1="{:.1}" x2="{:.1}" y2="{:.1}"/></g>"#,
+            x(min.east),
+            y(0.0),
+            x(max.east),
+            y(0.0),
+            x(0.0),
+            y(min.north),
+            x(0.0),
+            y(max.north)
+        )?;
+        writeln!(
+            out,
+            r#"  <g font-family="sans-serif" font-size="10" fill="gray"><text x="{:.1}" y="{:.1}" text-anchor="start">{}</text><text x="{:.1}" y="{:.1}" text-anchor="end">{}</text><text x="{:.1}" y="{:.1}">{}</text><text x="{:.1}" y="{:.1}">{}</text></g>"#,
+            x(min.east),
+            y(0.0) + 12.0,
+            min.east,
+            x(max.east),
+            y(0.0) + 12.0,
+            max.east,
+            x(0.0) + 4.0,
+            y(max.north) - 4.0,
+            max.north,
+            x(0.0) + 4.0,
+            y(min.north) + 12.0,
+            min.north
+        )?;
+
+        if !waypoints.is_empty() {
+            writeln!(
+                out,
+                r#"  <polyline class="waypoint" fill="none" stroke="orange" stroke-dasharray="4 2" points="{}"/>"#,
+                polyline(&mut waypoints.iter().copied())
+            )?;
+        }
+        writeln!(
+            out,
+            r#"  <polyline class="ship" fill="none" stroke="steelblue" stroke-width="2" points="{}"/>"#,
+            polyline(&mut self.points.iter().map(|p| p.position))
+        )?;
+        for p in self.points.iter().skip(1) {
+            let (cx, cy) = pixel(p.position);
+            writeln!(
+                out,
+                r#"  <circle cx="{:.1}" cy="{:.1}" r="2" fill="steelblue"><title>{}</title></circle>"#,
+                cx,
+                cy,
+                p.action.unwrap()
+            )?;
+        }
+
+        // start and end markers
+        let (start, end) = (self.points[0], self.points[self.points.len() - 1]);
+        for (p, color, label) in [(start, "green", "start"), (end, "red", "end")] {
+            let (cx, cy) = pixel(p.position);
+            writeln!(
+                out,
+                r#"  <circle class="{}" cx="{:.1}" cy="{:.1}" r="5" fill="{}"><title>{}</title></circle>"#,
+                label, cx, cy, color, label
+            )?;
+        }
+        writeln!(out, "</svg>")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::day12::navigator::{Ship, WaypointShip};
+    use crate::day12::{parse_input_day12, EXAMPLE};
+
+    #[test]
+    fn record_and_export_csv() {
+        let trajectory =
+            Trajectory::record(&mut Ship::<isize>::new(), &parse_input_day12(EXAMPLE)).unwrap();
+        assert_eq!(trajectory.points().len(), 6);
+        assert_eq!(trajectory.points()[2].position, Vector::new(10, 3));
+        assert_eq!(trajectory.points()[5].position, Vector::new(17, -8));
+
+        let mut csv = Vec::new();
+        trajectory.write_csv(&mut csv).unwrap();
+        assert_eq!(
+            String::from_utf8(csv).unwrap(),
+            "step,action,east,north,waypoint_east,waypoint_north
+0,,0,0,,
+1,F10,10,0,,
+2,N3,10,3,,
+3,F7,17,3,,
+4,R90,17,3,,
+5,F11,17,-8,,
+"
+        );
+
+        let trajectory = Trajectory::record(
+            &mut WaypointShip::<isize>::new(),
+            &parse_input_day12(EXAMPLE),
+        )
+        .unwrap();
+        let mut csv = Vec::new();
+        trajectory.write_csv(&mut csv).unwrap();
+        let csv = String::from_utf8(csv).unwrap();
+        assert!(csv.contains("\n0,,0,0,10,1\n"));
+        assert!(csv.ends_with("\n5,F11,214,-72,4,-10\n"));
+    }
+
+    #[test]
+    fn svg_export() {
+        let trajectory = Trajectory::record(
+            &mut WaypointShip::<isize>::new(),
+            &parse_input_day12(EXAMPLE),
+        )
+        .unwrap();
+        let mut svg = Vec::new();
+        trajectory.write_svg(&mut svg, 400.0).unwrap();
+        let svg = String::from_utf8(svg).unwrap();
+
+        assert!(svg.starts_with("<svg xmlns=\"http://www.w3.org/2000/svg\""));
+        assert!(svg.trim_end().ends_with("</svg>"));
+        assert_eq!(svg.matches("<polyline").count(), 2);
+        assert_eq!(svg.matches("<title>").count(), 5 + 2);
+        assert!(svg.contains("<title>R90</title>"));
+        // the ship never goes west of the origin, so it starts at the left margin
+        assert!(svg.contains(r#"<circle class="start" cx="40.0""#));
+        assert!(svg.contains(r#"<circle class="end""#));
+
+        let trajectory =
+            Trajectory::record(&mut Ship::<f64>::new(), &parse_input_day12("R45\nF10")).unwrap();
+        let mut svg = Vec::new();
+        trajectory.write_svg(&mut svg, 100.0).unwrap();
+        assert_eq!(
+            String::from_utf8(svg).unwrap().matches("<polyline").count(),
+            1
+        );
+    }
+}
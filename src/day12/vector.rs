@@ -0,0 +1,194 @@
+use core::fmt;
+use std::error::Error;
+use std::fmt::{Display, Formatter};
+use std::ops::{Add, AddAssign, Mul, Neg, Sub};
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum RotationError {
+    /// integer vectors can only be rotated by multiples of 90°
+    UnsupportedAngle(isize),
+}
+
+impl Display for RotationError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            RotationError::UnsupportedAngle(degrees) => write!(
+                f,
+                "cannot rotate by {}° without floating point, only multiples of 90° are supported",
+                degrees
+            ),
+        }
+    }
+}
+
+impl Error for RotationError {}
+
+/// The number types a [`Vector`] can be made of.
+pub trait Scalar:
+    Copy
+    + Default
+    + PartialEq
+    + Add<Output = Self>
+    + Sub<Output = Self>
+    + Mul<Output = Self>
+    + Neg<Output = Self>
+{
+    fn from_amount(amount: usize) -> Self;
+
+    fn to_f64(self) -> f64;
+
+    /// Rotates counterclockwise by `degrees`, clockwise if negative.
+    fn rotate(vector: Vector<Self>, degrees: isize) -> Result<Vector<Self>, RotationError>;
+}
+
+/// Rotates counterclockwise by a number of quarter turns, which is exact for every type.
+fn quarter_turns<T: Scalar>(vector: Vector<T>, turns: isize) -> Vector<T> {
+    let Vector { east, north } = vector;
+    match turns.rem_euclid(4) {
+        0 => Vector::new(east, north),
+        1 => Vector::new(-north, east),
+        2 => Vector::new(-east, -north),
+        _ => Vector::new(north, -east),
+    }
+}
+
+impl Scalar for isize {
+    fn from_amount(amount: usize) -> Self {
+        amount as isize
+    }
+
+    fn to_f64(self) -> f64 {
+        self as f64
+    }
+
+    fn rotate(vector: Vector<Self>, degrees: isize) -> Result<Vector<Self>, RotationError> {
+        if degrees % 90 != 0 {
+            return Err(RotationError::UnsupportedAngle(degrees));
+        }
+        Ok(quarter_turns(vector, degrees / 90))
+    }
+}
+
+impl Scalar for f64 {
+    fn from_amount(amount: usize) -> Self {
+        amount as f64
+    }
+
+    fn to_f64(self) -> f64 {
+        self
+    }
+
+    /// Exact for multiples of 90°, otherwise subject to the usual rounding errors.
+    fn rotate(vector: Vector<Self>, degrees: isize) -> Result<Vector<Self>, RotationError> {
+        if degrees % 90 == 0 {
+            return Ok(quarter_turns(vector, degrees / 90));
+        }
+        let (sin, cos) = (degrees as f64).to_radians().sin_cos();
+        Ok(Vector::new(
+            vector.east * cos - vector.north * sin,
+            vector.east * sin + vector.north * cos,
+        ))
+    }
+}
+
+/// A position or direction on the map, north and east are positive.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Hash)]
+pub struct Vector<T> {
+    pub east: T,
+    pub north: T,
+}
+
+impl<T> Vector<T> {
+    pub const fn new(east: T, north: T) -> Self {
+        Vector { east, north }
+    }
+}
+
+impl<T: Scalar> Vector<T> {
+    /// Rotates counterclockwise by `degrees`, clockwise if negative.
+    pub fn rotate(self, degrees: isize) -> Result<Self, RotationError> {
+        T::rotate(self, degrees)
+    }
+}
+
+impl Vector<isize> {
+    pub fn manhattan(&self) -> usize {
+        self.east.unsigned_abs() + self.north.unsigned_abs()
+    }
+}
+
+impl Vector<f64> {
+    pub fn manhattan(&self) -> f64 {
+        self.east.abs() + self.north.abs()
+    }
+}
+
+impl<T: Scalar> Add for Vector<T> {
+    type Output = Self;
+
+    fn add(self, other: Self) -> Self {
+        Vector::new(self.east + other.east, self.north + other.north)
+    }
+}
+
+impl<T: Scalar> AddAssign for Vector<T> {
+    fn add_assign(&mut self, other: Self) {
+        *self = *self + other;
+    }
+}
+
+impl<T: Scalar> Sub for Vector<T> {
+    type Output = Self;
+
+    fn sub(self, other: Self) -> Self {
+        Vector::new(self.east - other.east, self.north - other.north)
+    }
+}
+
+impl<T: Scalar> Mul<T> for Vector<T> {
+    type Output = Self;
+
+    fn mul(self, factor: T) -> Self {
+        Vector::new(self.east * factor, self.north * factor)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn integer_rotation() {
+        let v = Vector::new(10isize, 4);
+        assert_eq!(v.rotate(90), Ok(Vector::new(-4, 10)));
+        assert_eq!(v.rotate(-90), Ok(Vector::new(4, -10)));
+        assert_eq!(v.rotate(180), Ok(Vector::new(-10, -4)));
+        assert_eq!(v.rotate(-270), v.rotate(90));
+        assert_eq!(v.rotate(720), Ok(v));
+        assert_eq!(v.rotate(45), Err(RotationError::UnsupportedAngle(45)));
+        assert_eq!(v.rotate(-100), Err(RotationError::UnsupportedAngle(-100)));
+        assert_eq!(v.manhattan(), 14);
+    }
+
+    #[test]
+    fn float_rotation() {
+        let v = Vector::new(10.0, 4.0);
+        // multiples of 90° do not accumulate rounding errors
+        assert_eq!(v.rotate(90), Ok(Vector::new(-4.0, 10.0)));
+        assert_eq!(v.rotate(-450), Ok(Vector::new(4.0, -10.0)));
+
+        let rotated = Vector::new(1.0, 0.0).rotate(45).unwrap();
+        assert!((rotated.east - 0.5f64.sqrt()).abs() < 1e-12);
+        assert!((rotated.north - 0.5f64.sqrt()).abs() < 1e-12);
+        let back = rotated.rotate(-45).unwrap();
+        assert!((back - Vector::new(1.0, 0.0)).manhattan() < 1e-12);
+    }
+
+    #[test]
+    fn arithmetic() {
+        let mut v = Vector::new(1isize, 2);
+        v += Vector::new(3, -5) * 2;
+        assert_eq!(v, Vector::new(7, -8));
+        assert_eq!(v - Vector::new(7, 0), Vector::new(0, -8));
+    }
+}
@@ -1,104 +1,228 @@
+use crate::number_theory::{chinese_remainder, gcd, lcm, CrtError};
 use aoc_runner_derive::{aoc, aoc_generator};
+use core::fmt;
+use std::cmp::Reverse;
+use std::collections::BinaryHeap;
+use std::convert::TryFrom;
+use std::error::Error;
+use std::fmt::{Display, Formatter};
 
-struct Bus {
-    offset: usize,
-    interval: usize,
+#[derive(Clone, Debug, PartialEq)]
+pub enum ScheduleError {
+    MissingLine,
+    InvalidEarliest(String),
+    InvalidBus(String),
+    /// every bus is out of service
+    NoBusses,
+    /// the bus at this offset never leaves
+    ZeroInterval(usize),
+    /// `earliest_aligned` was asked for a bus that is not in the notes
+    UnknownBus(usize),
+    /// the busses never depart at the requested offsets or the time is too large
+    Alignment(CrtError),
 }
 
-struct Notes {
+impl Display for ScheduleError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            ScheduleError::MissingLine => write!(f, "expected the earliest time and the busses"),
+            ScheduleError::InvalidEarliest(s) => write!(f, "invalid earliest time '{}'", s),
+            ScheduleError::InvalidBus(s) => write!(f, "invalid bus '{}'", s),
+            ScheduleError::NoBusses => write!(f, "no bus is in service"),
+            ScheduleError::ZeroInterval(offset) => {
+                write!(f, "the bus at offset {} has an interval of 0", offset)
+            }
+            ScheduleError::UnknownBus(interval) => write!(f, "there is no bus {}", interval),
+            ScheduleError::Alignment(e) => e.fmt(f),
+        }
+    }
+}
+
+impl Error for ScheduleError {}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Bus {
+    /// position in the list, in minutes after the first bus for part 2
+    pub offset: usize,
+    /// the bus departs at every multiple of it, also its ID
+    pub interval: usize,
+}
+
+impl Bus {
+    /// first departure at or after `time`, `None` if it does not fit into a `usize`
+    /// or the bus never departs because its interval is 0
+    pub fn next_departure(&self, time: usize) -> Option<usize> {
+        if self.interval == 0 {
+            return None;
+        }
+        time.div_ceil(self.interval).checked_mul(self.interval)
+    }
+}
+
+/// A departure of a bus, ordered by time and then by position in the notes.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Departure<'a> {
+    pub time: usize,
+    pub bus: &'a Bus,
+}
+
+/// All departures from a time on in chronological order, see [`Notes::departures`].
+/// A bus drops out once its departures no longer fit into a `usize`.
+pub struct Departures<'a> {
+    busses: &'a [Bus],
+    /// next departure of every bus, by index
+    queue: BinaryHeap<Reverse<(usize, usize)>>,
+}
+
+impl<'a> Iterator for Departures<'a> {
+    type Item = Departure<'a>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let Reverse((time, index)) = self.queue.pop()?;
+        let bus = &self.busses[index];
+        if let Some(next) = time.checked_add(bus.interval) {
+            self.queue.push(Reverse((next, index)));
+        }
+        Some(Departure { time, bus })
+    }
+}
+
+/// The notes of the puzzle, with at least one bus and no bus with an interval of 0.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Notes {
     earliest: usize,
     busses: Vec<Bus>,
 }
 
+impl Notes {
+    pub fn new(earliest: usize, busses: Vec<Bus>) -> Result<Self, ScheduleError> {
+        if busses.is_empty() {
+            return Err(ScheduleError::NoBusses);
+        }
+        if let Some(bus) = busses.iter().find(|bus| bus.interval == 0) {
+            return Err(ScheduleError::ZeroInterval(bus.offset));
+        }
+        Ok(Notes { earliest, busses })
+    }
+
+    pub fn earliest(&self) -> usize {
+        self.earliest
+    }
+
+    pub fn busses(&self) -> &[Bus] {
+        &self.busses
+    }
+
+    /// The first departure of every bus at or after `time`, in the order of the notes.
+    /// Busses whose next departure does not fit into a `usize` are left out.
+    pub fn next_departures(&self, time: usize) -> impl Iterator<Item = Departure<'_>> {
+        self.busses.iter().filter_map(move |bus| {
+            Some(Departure {
+                time: bus.next_departure(time)?,
+                bus,
+            })
+        })
+    }
+
+    /// Iterator over the departures of all busses at or after `time`, use `take(k)` for
+    /// the next `k`. Busses leaving at the same time come in the order of the notes.
+    /// It only ends when the times no longer fit into a `usize`.
+    pub fn departures(&self, time: usize) -> Departures<'_> {
+        Departures {
+            busses: &self.busses,
+            queue: self
+                .busses
+                .iter()
+                .enumerate()
+                .filter_map(|(index, bus)| Some(Reverse((bus.next_departure(time)?, index))))
+                .collect(),
+        }
+    }
+
+    /// Earliest time `t` at which every bus with the given interval departs at `t + offset`.
+    pub fn earliest_aligned(&self, offsets: &[(usize, usize)]) -> Result<usize, ScheduleError> {
+        let equations = offsets
+            .iter()
+            .map(|&(interval, offset)| {
+                if !self.busses.iter().any(|bus| bus.interval == interval) {
+                    return Err(ScheduleError::UnknownBus(interval));
+                }
+                let m = interval as i128;
+                // t mod m ≡ -offset
+                Ok((m, (-(offset as i128)).rem_euclid(m)))
+            })
+            .collect::<Result<Vec<(i128, i128)>, ScheduleError>>()?;
+
+        let t = chinese_remainder(&equations).map_err(ScheduleError::Alignment)?;
+        usize::try_from(t).map_err(|_| ScheduleError::Alignment(CrtError::Overflow))
+    }
+}
+
 #[aoc_generator(day13)]
-fn parse_input_day13(input: &str) -> Notes {
-    let lines = input.lines().collect::<Vec<&str>>();
-    let earliest = lines[0].parse().unwrap();
-    let busses = lines[1]
-        .split(",")
+fn parse_input_day13(input: &str) -> Result<Notes, ScheduleError> {
+    let mut lines = input.lines();
+    let earliest = lines.next().ok_or(ScheduleError::MissingLine)?;
+    let earliest = earliest
+        .parse()
+        .map_err(|_| ScheduleError::InvalidEarliest(earliest.to_string()))?;
+    let busses = lines
+        .next()
+        .ok_or(ScheduleError::MissingLine)?
+        .split(',')
         .enumerate()
-        .filter_map(|(offset, bus)| {
-            Some(Bus {
+        .filter(|(_, bus)| *bus != "x")
+        .map(|(offset, bus)| {
+            Ok(Bus {
                 offset,
-                interval: bus.parse().ok()?,
+                interval: bus
+                    .parse()
+                    .map_err(|_| ScheduleError::InvalidBus(bus.to_string()))?,
             })
         })
-        .collect();
-    Notes { earliest, busses }
+        .collect::<Result<Vec<Bus>, ScheduleError>>()?;
+    Notes::new(earliest, busses)
 }
 
 #[aoc(day13, part1)]
-fn part1(notes: &Notes) -> usize {
-    let mut t = notes.earliest;
-    loop {
-        for bus in &notes.busses {
-            if t % bus.interval == 0 {
-                return bus.interval * (t - notes.earliest);
-            }
-        }
-        t += 1;
-    }
+fn part1(notes: &Notes) -> Option<usize> {
+    let departure = notes.departures(notes.earliest).next()?;
+    Some(departure.bus.interval * (departure.time - notes.earliest))
 }
 
 #[aoc(day13, part2)]
-fn part2(notes: &Notes) -> usize {
+fn part2(notes: &Notes) -> Option<usize> {
     let mut t = 0;
-    let mut common_interval = 1;
+    // `None` once it overflows, which only matters if another bus follows
+    let mut common_interval = Some(1);
 
     for bus in &notes.busses {
-        while (t + bus.offset) % bus.interval != 0 {
+        let common = common_interval?;
+        // t has to be a multiple of the interval after subtracting the offset
+        let target = (bus.interval - bus.offset % bus.interval) % bus.interval;
+        // the steps below keep t modulo the gcd, so if that does not fit there is no solution
+        let d = gcd(common, bus.interval);
+        if t % d != target % d {
+            return None;
+        }
+        while t % bus.interval != target {
             // increase time in steps that is a multiple of all previous bus intervals
-            t += common_interval;
+            t = t.checked_add(common)?;
         }
         // make the common interval include the current bus interval
-        common_interval *= bus.interval;
+        common_interval = lcm(common, bus.interval);
     }
 
-    t
-}
-
-fn extended_euclid(a: isize, b: isize) -> (isize, isize, isize) {
-    if b == 0 {
-        return (a, 1, 0);
-    }
-    let (d_, s_, t_) = extended_euclid(b, a % b);
-    let (d, s, t) = (d_, t_, s_ - (a / b) * t_);
-    // d = s * a + t * b
-    return (d, s, t);
-}
-
-fn chinese_remainder_theorem(equations: &Vec<(isize, isize)>) -> usize {
-    // least common multiple of moduli
-    let lcm = equations.iter().map(|(m, _x)| m).product::<isize>();
-
-    let solution = equations
-        .iter()
-        .map(|(eq_m, eq_x)| {
-            let m = lcm / eq_m;
-            // modular inverse of m
-            let e = extended_euclid(m, *eq_m).1;
-            // partial solution for equation
-            e * m * eq_x
-        })
-        .sum::<isize>();
-
-    solution.rem_euclid(lcm) as usize
+    Some(t)
 }
 
 #[aoc(day13, part2, equations)]
-fn part2_equations(notes: &Notes) -> usize {
-    let equations = notes
+fn part2_equations(notes: &Notes) -> Result<usize, ScheduleError> {
+    let offsets = notes
         .busses
         .iter()
-        .map(|bus| {
-            let m = bus.interval as isize;
-            let x = (-(bus.offset as isize)).rem_euclid(m);
-            // t mod m ≡ x
-            (m, x)
-        })
-        .collect();
-
-    chinese_remainder_theorem(&equations)
+        .map(|bus| (bus.interval, bus.offset))
+        .collect::<Vec<(usize, usize)>>();
+    notes.earliest_aligned(&offsets)
 }
 
 #[cfg(test)]
@@ -110,46 +234,216 @@ mod tests {
 
     #[test]
     fn part1_example() {
-        assert_eq!(part1(&parse_input_day13(INPUT)), 295);
+        assert_eq!(part1(&parse_input_day13(INPUT).unwrap()), Some(295));
     }
 
     #[test]
     fn part2_crt_test() {
-        assert_eq!(chinese_remainder_theorem(&vec![(3, 2), (4, 3), (5, 2)]), 47);
+        assert_eq!(chinese_remainder(&[(3i128, 2), (4, 3), (5, 2)]), Ok(47));
     }
 
     #[test]
     fn part2_equations_example() {
-        assert_eq!(part2_equations(&parse_input_day13(INPUT)), 1068781);
+        assert_eq!(
+            part2_equations(&parse_input_day13(INPUT).unwrap()),
+            Ok(1068781)
+        );
     }
 
     #[test]
     fn part2_example_1() {
-        assert_eq!(part2(&parse_input_day13(INPUT)), 1068781);
+        assert_eq!(part2(&parse_input_day13(INPUT).unwrap()), Some(1068781));
     }
 
     #[test]
     fn part2_example_2() {
-        assert_eq!(part2(&parse_input_day13("0\n17,x,13,19")), 3417);
+        assert_eq!(
+            part2(&parse_input_day13("0\n17,x,13,19").unwrap()),
+            Some(3417)
+        );
     }
 
     #[test]
     fn part2_example_3() {
-        assert_eq!(part2(&parse_input_day13("0\n67,7,59,61")), 754018);
+        assert_eq!(
+            part2(&parse_input_day13("0\n67,7,59,61").unwrap()),
+            Some(754018)
+        );
     }
 
     #[test]
     fn part2_example_4() {
-        assert_eq!(part2(&parse_input_day13("0\n67,x,7,59,61")), 779210);
+        assert_eq!(
+            part2(&parse_input_day13("0\n67,x,7,59,61").unwrap()),
+            Some(779210)
+        );
     }
 
     #[test]
     fn part2_example_5() {
-        assert_eq!(part2(&parse_input_day13("0\n67,7,x,59,61")), 1261476);
+        assert_eq!(
+            part2(&parse_input_day13("0\n67,7,x,59,61").unwrap()),
+            Some(1261476)
+        );
     }
 
     #[test]
     fn part2_example_6() {
-        assert_eq!(part2(&parse_input_day13("0\n1789,37,47,1889")), 1202161486);
+        assert_eq!(
+            part2(&parse_input_day13("0\n1789,37,47,1889").unwrap()),
+            Some(1202161486)
+        );
+    }
+
+    #[test]
+    fn part2_shared_factors() {
+        // t ≡ 0 (mod 6) and t + 2 ≡ 0 (mod 4)
+        let notes = parse_input_day13("0\n6,x,4").unwrap();
+        assert_eq!(part2(&notes), Some(6));
+        assert_eq!(part2_equations(&notes), Ok(6));
+
+        let notes = parse_input_day13("0\n4,x,6,x,x,x,x,x,x,x,10").unwrap();
+        assert_eq!(part2(&notes), Some(40));
+        assert_eq!(part2_equations(&notes), Ok(40));
+
+        // t would have to be even and odd
+        let notes = parse_input_day13("0\n6,4").unwrap();
+        assert_eq!(part2(&notes), None);
+        assert_eq!(
+            part2_equations(&notes),
+            Err(ScheduleError::Alignment(CrtError::Inconsistent))
+        );
+    }
+
+    #[test]
+    fn part2_large_intervals() {
+        // t = 3 * large is the largest usize, the steps to it must not overflow on the way
+        let large = usize::MAX / 3;
+        let notes = Notes::new(
+            0,
+            vec![
+                Bus {
+                    offset: 0,
+                    interval: large,
+                },
+                Bus {
+                    offset: 1,
+                    interval: 4,
+                },
+            ],
+        )
+        .unwrap();
+        assert_eq!(part2(&notes), Some(usize::MAX));
+
+        // no solution, found without trying every multiple of 2 below the large interval
+        let notes = Notes::new(
+            0,
+            vec![
+                Bus {
+                    offset: 0,
+                    interval: 2,
+                },
+                Bus {
+                    offset: 1,
+                    interval: usize::MAX - 1,
+                },
+            ],
+        )
+        .unwrap();
+        assert_eq!(part2(&notes), None);
+
+        // the solution does not fit into a usize
+        let notes = Notes::new(
+            0,
+            vec![
+                Bus {
+                    offset: 0,
+                    interval: usize::MAX,
+                },
+                Bus {
+                    offset: 2,
+                    interval: 4,
+                },
+            ],
+        )
+        .unwrap();
+        assert_eq!(part2(&notes), None);
+    }
+
+    #[test]
+    fn timetable() {
+        let notes = parse_input_day13(INPUT).unwrap();
+        let next = notes
+            .next_departures(939)
+            .map(|departure| (departure.bus.interval, departure.time))
+            .collect::<Vec<(usize, usize)>>();
+        assert_eq!(
+            next,
+            vec![(7, 945), (13, 949), (59, 944), (31, 961), (19, 950)]
+        );
+        assert_eq!(notes.busses()[0].next_departure(945), Some(945));
+        assert_eq!(notes.busses()[0].next_departure(usize::MAX), None);
+        assert_eq!(notes.next_departures(usize::MAX).count(), 0);
+        let broken = Bus {
+            offset: 0,
+            interval: 0,
+        };
+        assert_eq!(broken.next_departure(0), None);
+
+        let upcoming = notes
+            .departures(939)
+            .take(5)
+            .map(|departure| (departure.bus.interval, departure.time))
+            .collect::<Vec<(usize, usize)>>();
+        assert_eq!(
+            upcoming,
+            vec![(59, 944), (7, 945), (13, 949), (19, 950), (7, 952)]
+        );
+        // 0 is a departure of every bus
+        assert!(notes
+            .departures(0)
+            .take(5)
+            .all(|departure| departure.time == 0));
+        // the iterator ends instead of overflowing
+        let last = usize::MAX / 7 * 7;
+        let late = notes
+            .departures(last - 7)
+            .filter(|departure| departure.bus.interval == 7)
+            .map(|departure| departure.time)
+            .collect::<Vec<usize>>();
+        assert_eq!(late, vec![last - 7, last]);
+
+        assert_eq!(notes.earliest_aligned(&[(7, 0), (13, 1)]), Ok(77));
+        assert_eq!(notes.earliest_aligned(&[(59, 4)]), Ok(55));
+        assert_eq!(notes.earliest_aligned(&[]), Ok(0));
+        assert_eq!(
+            notes.earliest_aligned(&[(11, 0)]),
+            Err(ScheduleError::UnknownBus(11))
+        );
+    }
+
+    #[test]
+    fn invalid_schedules() {
+        assert_eq!(
+            parse_input_day13("939\nx,x").unwrap_err(),
+            ScheduleError::NoBusses
+        );
+        assert_eq!(
+            parse_input_day13("939\n7,x,0").unwrap_err(),
+            ScheduleError::ZeroInterval(2)
+        );
+        assert_eq!(
+            parse_input_day13("939\n7,?").unwrap_err(),
+            ScheduleError::InvalidBus("?".to_string())
+        );
+        assert_eq!(
+            parse_input_day13("soon\n7").unwrap_err(),
+            ScheduleError::InvalidEarliest("soon".to_string())
+        );
+        assert_eq!(
+            parse_input_day13("939").unwrap_err(),
+            ScheduleError::MissingLine
+        );
+        assert_eq!(Notes::new(0, vec![]), Err(ScheduleError::NoBusses));
     }
 }
@@ -1,89 +1,323 @@
 use aoc_runner_derive::{aoc, aoc_generator};
-use std::str::FromStr;
+use core::fmt;
+use decoder::{AddressDecoder, MaskSemantics, ValueDecoder};
+use floating::FloatingMemory;
 use std::collections::HashMap;
+use std::error::Error;
+use std::fmt::{Display, Formatter};
 
-enum Operation {
-    UpdateMask(usize, usize),
-    WriteValue(usize, usize),
+pub mod decoder;
+pub mod floating;
+
+/// word width of the puzzle's machine
+pub const DEFAULT_WIDTH: u32 = 36;
+/// widest word that fits into the `u128` the machine computes with
+pub const MAX_WIDTH: u32 = 128;
+
+/// all bits of a word with `width` bits set
+fn word(width: u32) -> u128 {
+    u128::MAX >> (MAX_WIDTH - width)
 }
 
-impl FromStr for Operation {
-    type Err = ();
+/// A bitmask, every bit is either `0`, `1` or floating (`X`).
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct Mask {
+    width: u32,
+    ones: u128,
+    floating: u128,
+}
 
-    fn from_str(s: &str) -> Result<Self, Self::Err> {
-        Ok(match &s[1..2] {
-            "a" => Operation::UpdateMask(
-                usize::from_str_radix(&s[7..].replace('X', "1"), 2).map_err(|_| ())?,
-                usize::from_str_radix(&s[7..].replace('X', "0"), 2).map_err(|_| ())?,
-            ),
-            "e" => Operation::WriteValue(
-                s[4..s.find(']').unwrap()].parse().map_err(|_| ())?,
-                s[(s.find('=').unwrap() + 2)..].parse().map_err(|_| ())?,
-            ),
-            _ => return Err(()),
+impl Mask {
+    /// all bits `0`, the mask before the first `mask = ...`, `None` for an unsupported width
+    pub fn zeros(width: u32) -> Option<Self> {
+        if !(1..=MAX_WIDTH).contains(&width) {
+            return None;
+        }
+        Some(Mask {
+            width,
+            ones: 0,
+            floating: 0,
         })
     }
+
+    /// Parses the most significant bit first, the width is the number of characters.
+    pub fn parse(s: &str) -> Result<Self, ParseErrorKind> {
+        if s.is_empty() || s.len() > MAX_WIDTH as usize {
+            return Err(ParseErrorKind::UnsupportedWidth(s.len()));
+        }
+        let mut mask = Mask {
+            width: s.len() as u32,
+            ones: 0,
+            floating: 0,
+        };
+        for c in s.chars() {
+            mask.ones <<= 1;
+            mask.floating <<= 1;
+            match c {
+                '0' => {}
+                '1' => mask.ones |= 1,
+                'X' => mask.floating |= 1,
+                _ => return Err(ParseErrorKind::InvalidMaskBit(c)),
+            }
+        }
+        Ok(mask)
+    }
+
+    pub fn width(&self) -> u32 {
+        self.width
+    }
+
+    /// bits that are `1`
+    pub fn ones(&self) -> u128 {
+        self.ones
+    }
+
+    /// bits that are `0`
+    pub fn zeros_mask(&self) -> u128 {
+        word(self.width) & !self.ones & !self.floating
+    }
+
+    /// bits that are `X`
+    pub fn floating(&self) -> u128 {
+        self.floating
+    }
 }
 
-#[aoc_generator(day14)]
-fn parse_input_day14(input: &str) -> Vec<Operation> {
-    input.lines().filter_map(|line| Operation::from_str(line).ok()).collect()
+impl Display for Mask {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        for bit in (0..self.width).rev() {
+            let c = if self.ones >> bit & 1 == 1 {
+                '1'
+            } else if self.floating >> bit & 1 == 1 {
+                'X'
+            } else {
+                '0'
+            };
+            write!(f, "{}", c)?;
+        }
+        Ok(())
+    }
 }
 
-#[aoc(day14, part1)]
-fn part1(ops: &Vec<Operation>) -> usize {
-    let mut mem: HashMap<usize, usize> = HashMap::new();
-
-    let mut mask = (0, 0);
-    for op in ops {
-        match op {
-            Operation::UpdateMask(one_mask, zero_mask) => {
-                mask = (*one_mask, *zero_mask);
-            },
-            Operation::WriteValue(address, value) => {
-                mem.insert(*address, *value & mask.0 | mask.1);
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum Operation {
+    UpdateMask(Mask),
+    WriteValue { address: u128, value: u128 },
+}
+
+impl Operation {
+    /// Parses `mask = <mask>` or `mem[<address>] = <value>` for a machine with `width` bits.
+    pub fn parse(s: &str, width: u32) -> Result<Self, ParseErrorKind> {
+        if !(1..=MAX_WIDTH).contains(&width) {
+            return Err(ParseErrorKind::UnsupportedWidth(width as usize));
+        }
+        let s = s.trim();
+        if let Some(mask) = s.strip_prefix("mask = ") {
+            let mask = Mask::parse(mask)?;
+            if mask.width != width {
+                return Err(ParseErrorKind::MaskWidth {
+                    expected: width,
+                    found: mask.width,
+                });
             }
+            Ok(Operation::UpdateMask(mask))
+        } else if let Some(write) = s.strip_prefix("mem[") {
+            let (address, value) = write
+                .split_once("] = ")
+                .ok_or_else(|| ParseErrorKind::UnknownInstruction(s.to_string()))?;
+            let number = |n: &str| n.parse::<u128>().ok().filter(|n| n & !word(width) == 0);
+            Ok(Operation::WriteValue {
+                address: number(address)
+                    .ok_or_else(|| ParseErrorKind::InvalidAddress(address.to_string()))?,
+                value: number(value)
+                    .ok_or_else(|| ParseErrorKind::InvalidValue(value.to_string()))?,
+            })
+        } else {
+            Err(ParseErrorKind::UnknownInstruction(s.to_string()))
         }
     }
+}
 
-    mem.values().sum()
+#[derive(Clone, Debug, PartialEq)]
+pub enum ParseErrorKind {
+    UnknownInstruction(String),
+    /// masks need between 1 and [`MAX_WIDTH`] bits
+    UnsupportedWidth(usize),
+    InvalidMaskBit(char),
+    /// the mask does not have as many bits as the machine
+    MaskWidth {
+        expected: u32,
+        found: u32,
+    },
+    /// not a number or wider than a word
+    InvalidAddress(String),
+    /// not a number or wider than a word
+    InvalidValue(String),
 }
 
-fn mask_address(address: usize, x_mask: usize) -> Vec<usize> {
-    let mut addresses = Vec::with_capacity(2usize.pow(x_mask.count_ones()));
-    addresses.push(address);
-    for pos in 0..36 {
-        let pos_mask = 1 << pos;
-        if (x_mask & pos_mask) > 0 {
-            for i in 0..addresses.len() {
-                addresses.push(addresses[i] ^ pos_mask);
+impl Display for ParseErrorKind {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            ParseErrorKind::UnknownInstruction(s) => write!(f, "unknown instruction '{}'", s),
+            ParseErrorKind::UnsupportedWidth(width) => write!(
+                f,
+                "unsupported width of {} bits, at most {} are possible",
+                width, MAX_WIDTH
+            ),
+            ParseErrorKind::InvalidMaskBit(c) => write!(f, "invalid mask bit '{}'", c),
+            ParseErrorKind::MaskWidth { expected, found } => {
+                write!(f, "expected a mask with {} bits, found {}", expected, found)
             }
+            ParseErrorKind::InvalidAddress(s) => write!(f, "invalid address '{}'", s),
+            ParseErrorKind::InvalidValue(s) => write!(f, "invalid value '{}'", s),
         }
     }
-    addresses
 }
 
-#[aoc(day14, part2)]
-fn part2(ops: &Vec<Operation>) -> usize {
-    let mut mem: HashMap<usize, usize> = HashMap::new();
-
-    let mut mask = (0, 0);
-    for op in ops {
-        match op {
-            Operation::UpdateMask(one_mask, zero_mask) => {
-                mask = (*one_mask, *zero_mask);
-            },
-            Operation::WriteValue(address, value) => {
-                let x_mask = (mask.0 ^ mask.1) & ((1 << 36) - 1);
-                let address_masked_ones = *address | mask.1;
-                for possible_address in mask_address(address_masked_ones, x_mask) {
-                    mem.insert(possible_address, *value);
-                }
+#[derive(Clone, Debug, PartialEq)]
+pub struct ParseError {
+    /// starting at 1
+    pub line: usize,
+    pub kind: ParseErrorKind,
+}
+
+impl Display for ParseError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "line {}: {}", self.line, self.kind)
+    }
+}
+
+impl Error for ParseError {}
+
+/// Parses a program for a machine with `width` bits, skipping empty lines.
+pub fn parse_program(input: &str, width: u32) -> Result<Vec<Operation>, ParseError> {
+    if !(1..=MAX_WIDTH).contains(&width) {
+        return Err(ParseError {
+            line: 0,
+            kind: ParseErrorKind::UnsupportedWidth(width as usize),
+        });
+    }
+    input
+        .lines()
+        .enumerate()
+        .filter(|(_, line)| !line.trim().is_empty())
+        .map(|(index, line)| {
+            Operation::parse(line, width).map_err(|kind| ParseError {
+                line: index + 1,
+                kind,
+            })
+        })
+        .collect()
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum MachineError {
+    /// the machine's word width is 0 or above [`MAX_WIDTH`]
+    UnsupportedWidth(u32),
+    /// a write would go to more addresses than can be expanded, see
+    /// [`MAX_FLOATING_BITS`](decoder::MAX_FLOATING_BITS)
+    TooManyFloatingBits(u32),
+    /// the sum of the memory does not fit into a `u128`
+    SumOverflow,
+}
+
+impl Display for MachineError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            MachineError::UnsupportedWidth(width) => write!(
+                f,
+                "unsupported width of {} bits, at most {} are possible",
+                width, MAX_WIDTH
+            ),
+            MachineError::TooManyFloatingBits(bits) => write!(
+                f,
+                "cannot expand a write with {} floating bits, at most {} are possible",
+                bits,
+                decoder::MAX_FLOATING_BITS
+            ),
+            MachineError::SumOverflow => write!(f, "the sum does not fit into 128 bits"),
+        }
+    }
+}
+
+impl Error for MachineError {}
+
+/// The docking program's memory, written according to the semantics of a decoder chip.
+pub struct Machine<S> {
+    semantics: S,
+    mask: Mask,
+    memory: HashMap<u128, u128>,
+}
+
+impl<S: MaskSemantics> Machine<S> {
+    /// Fails for a width the machine cannot compute with.
+    pub fn new(semantics: S, width: u32) -> Result<Self, MachineError> {
+        Ok(Machine {
+            semantics,
+            mask: Mask::zeros(width).ok_or(MachineError::UnsupportedWidth(width))?,
+            memory: HashMap::new(),
+        })
+    }
+
+    /// Executes one operation, a failed write leaves the memory unchanged.
+    pub fn execute(&mut self, op: &Operation) -> Result<(), MachineError> {
+        match *op {
+            Operation::UpdateMask(mask) => self.mask = mask,
+            Operation::WriteValue { address, value } => {
+                self.semantics
+                    .write(&mut self.memory, &self.mask, address, value)?;
             }
         }
+        Ok(())
     }
 
-    mem.values().sum()
+    /// Executes all operations, stopping at the first one that fails.
+    pub fn run(&mut self, ops: &[Operation]) -> Result<&mut Self, MachineError> {
+        for op in ops {
+            self.execute(op)?;
+        }
+        Ok(self)
+    }
+
+    pub fn memory(&self) -> &HashMap<u128, u128> {
+        &self.memory
+    }
+
+    /// Sum of all values in memory, `None` if it does not fit into a `u128`.
+    pub fn sum(&self) -> Option<u128> {
+        self.memory
+            .values()
+            .try_fold(0u128, |sum, value| sum.checked_add(*value))
+    }
+}
+
+#[aoc_generator(day14)]
+fn parse_input_day14(input: &str) -> Result<Vec<Operation>, ParseError> {
+    parse_program(input, DEFAULT_WIDTH)
+}
+
+#[aoc(day14, part1)]
+fn part1(ops: &[Operation]) -> Result<u128, MachineError> {
+    Machine::new(ValueDecoder, DEFAULT_WIDTH)?
+        .run(ops)?
+        .sum()
+        .ok_or(MachineError::SumOverflow)
+}
+
+#[aoc(day14, part2)]
+fn part2(ops: &[Operation]) -> Result<u128, MachineError> {
+    Machine::new(AddressDecoder, DEFAULT_WIDTH)?
+        .run(ops)?
+        .sum()
+        .ok_or(MachineError::SumOverflow)
+}
+
+#[aoc(day14, part2, symbolic)]
+fn part2_symbolic(ops: &[Operation]) -> Result<u128, MachineError> {
+    FloatingMemory::new()
+        .run(ops, DEFAULT_WIDTH)?
+        .sum()
+        .ok_or(MachineError::SumOverflow)
 }
 
 #[cfg(test)]
@@ -96,7 +330,7 @@ mod tests {
 mem[8] = 11
 mem[7] = 101
 mem[8] = 0";
-        assert_eq!(part1(&parse_input_day14(input)), 165);
+        assert_eq!(part1(&parse_input_day14(input).unwrap()), Ok(165));
     }
 
     #[test]
@@ -105,6 +339,88 @@ mem[8] = 0";
 mem[42] = 100
 mask = 00000000000000000000000000000000X0XX
 mem[26] = 1";
-        assert_eq!(part2(&parse_input_day14(input)), 208);
+        assert_eq!(part2(&parse_input_day14(input).unwrap()), Ok(208));
+        assert_eq!(part2_symbolic(&parse_input_day14(input).unwrap()), Ok(208));
+    }
+
+    #[test]
+    fn parse_masks() {
+        let mask = Mask::parse("X1001X").unwrap();
+        assert_eq!(mask.width(), 6);
+        assert_eq!(mask.ones(), 0b010010);
+        assert_eq!(mask.floating(), 0b100001);
+        assert_eq!(mask.zeros_mask(), 0b001100);
+        assert_eq!(mask.to_string(), "X1001X");
+
+        let wide = "X".repeat(128);
+        assert_eq!(Mask::parse(&wide).unwrap().floating(), u128::MAX);
+        assert_eq!(
+            Mask::parse(&"0".repeat(129)),
+            Err(ParseErrorKind::UnsupportedWidth(129))
+        );
+        assert_eq!(Mask::parse("01?"), Err(ParseErrorKind::InvalidMaskBit('?')));
+        assert_eq!(Mask::zeros(0), None);
+        assert_eq!(Mask::zeros(129), None);
+    }
+
+    #[test]
+    fn unsupported_widths() {
+        for &width in &[0, 129] {
+            assert_eq!(
+                Machine::new(ValueDecoder, width).err(),
+                Some(MachineError::UnsupportedWidth(width))
+            );
+            assert_eq!(
+                FloatingMemory::new().run(&[], width).err(),
+                Some(MachineError::UnsupportedWidth(width))
+            );
+        }
+    }
+
+    #[test]
+    fn parse_errors() {
+        let error = |input: &str, width| parse_program(input, width).unwrap_err();
+        assert_eq!(
+            error("mask = X1\nmem[3] = 1\nmas", 2),
+            ParseError {
+                line: 3,
+                kind: ParseErrorKind::UnknownInstruction("mas".to_string())
+            }
+        );
+        assert_eq!(
+            error("mask = X1", 4).kind,
+            ParseErrorKind::MaskWidth {
+                expected: 4,
+                found: 2
+            }
+        );
+        assert_eq!(
+            error("mem[16] = 1", 4).kind,
+            ParseErrorKind::InvalidAddress("16".to_string())
+        );
+        assert_eq!(
+            error("mem[15] = -1", 4).kind,
+            ParseErrorKind::InvalidValue("-1".to_string())
+        );
+        assert_eq!(
+            error("mem[15] 1", 4).kind,
+            ParseErrorKind::UnknownInstruction("mem[15] 1".to_string())
+        );
+        assert_eq!(error("", 0).kind, ParseErrorKind::UnsupportedWidth(0));
+        assert_eq!(
+            Operation::parse("mem[0] = 1", 0),
+            Err(ParseErrorKind::UnsupportedWidth(0))
+        );
+        assert_eq!(
+            Operation::parse("mem[0] = 1", 129),
+            Err(ParseErrorKind::UnsupportedWidth(129))
+        );
+        assert_eq!(
+            parse_program("\nmem[15] = 15\n", 4),
+            Ok(vec![Operation::WriteValue {
+                address: 15,
+                value: 15
+            }])
+        );
     }
 }
@@ -0,0 +1,143 @@
+use super::{MachineError, Mask};
+use std::collections::HashMap;
+
+/// How a version of the decoder chip applies the mask when writing to memory.
+pub trait MaskSemantics {
+    /// Executes `mem[address] = value` with `mask`.
+    fn write(
+        &self,
+        memory: &mut HashMap<u128, u128>,
+        mask: &Mask,
+        address: u128,
+        value: u128,
+    ) -> Result<(), MachineError>;
+}
+
+/// Version 1: the mask overwrites bits of the value, `X` leaves them unchanged (part 1).
+#[derive(Copy, Clone, Debug, Default)]
+pub struct ValueDecoder;
+
+impl MaskSemantics for ValueDecoder {
+    fn write(
+        &self,
+        memory: &mut HashMap<u128, u128>,
+        mask: &Mask,
+        address: u128,
+        value: u128,
+    ) -> Result<(), MachineError> {
+        memory.insert(address, value & !mask.zeros_mask() | mask.ones());
+        Ok(())
+    }
+}
+
+/// Version 2: `1` bits of the mask overwrite bits of the address, `X` bits take all
+/// possible values so that one write goes to many addresses (part 2).
+#[derive(Copy, Clone, Debug, Default)]
+pub struct AddressDecoder;
+
+/// most floating bits a write can have before it is too large to expand, 2^24 addresses
+/// already take 256 MiB, see [`FloatingMemory`](super::floating::FloatingMemory) for more
+pub const MAX_FLOATING_BITS: u32 = 24;
+
+/// Every address that results from setting the `floating` bits of `address` in all ways,
+/// fails for more than [`MAX_FLOATING_BITS`] floating bits.
+pub fn floating_addresses(address: u128, floating: u128) -> Result<Vec<u128>, MachineError> {
+    if floating.count_ones() > MAX_FLOATING_BITS {
+        return Err(MachineError::TooManyFloatingBits(floating.count_ones()));
+    }
+    let mut addresses = Vec::with_capacity(1 << floating.count_ones());
+    addresses.push(address & !floating);
+    for pos in 0..u128::BITS {
+        let pos_mask = 1 << pos;
+        if floating & pos_mask != 0 {
+            for i in 0..addresses.len() {
+                addresses.push(addresses[i] | pos_mask);
+            }
+        }
+    }
+    Ok(addresses)
+}
+
+impl MaskSemantics for AddressDecoder {
+    fn write(
+        &self,
+        memory: &mut HashMap<u128, u128>,
+        mask: &Mask,
+        address: u128,
+        value: u128,
+    ) -> Result<(), MachineError> {
+        for address in floating_addresses(address | mask.ones(), mask.floating())? {
+            memory.insert(address, value);
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::day14::{parse_program, Machine};
+
+    #[test]
+    fn floating() {
+        let mut addresses = floating_addresses(0b11010, 0b100001).unwrap();
+        addresses.sort_unstable();
+        assert_eq!(addresses, vec![0b011010, 0b011011, 0b111010, 0b111011]);
+    }
+
+    #[test]
+    fn custom_widths() {
+        let ops = parse_program("mask = 1X0\nmem[1] = 7\nmem[0] = 2", 3).unwrap();
+        assert_eq!(
+            Machine::new(ValueDecoder, 3)
+                .unwrap()
+                .run(&ops)
+                .unwrap()
+                .sum(),
+            Some(6 + 6)
+        );
+        // writes 7 to 5 and 7, then 2 to 4 and 6
+        assert_eq!(
+            Machine::new(AddressDecoder, 3)
+                .unwrap()
+                .run(&ops)
+                .unwrap()
+                .sum(),
+            Some(7 + 7 + 2 + 2)
+        );
+
+        let mask = format!("mask = 1{}", "X".repeat(127));
+        let ops = parse_program(&format!("{}\nmem[0] = 1", mask), 128).unwrap();
+        let mut machine = Machine::new(ValueDecoder, 128).unwrap();
+        assert_eq!(machine.run(&ops).unwrap().sum(), Some(1 << 127 | 1));
+
+        // a single floating bit at the top doubles the writes
+        let ops = parse_program(&format!("mask = X{}\nmem[5] = 3", "0".repeat(127)), 128).unwrap();
+        let mut machine = Machine::new(AddressDecoder, 128).unwrap();
+        assert_eq!(machine.run(&ops).unwrap().memory().len(), 2);
+        assert!(machine.memory().contains_key(&(1 << 127 | 5)));
+    }
+
+    #[test]
+    fn limits() {
+        // expanding too many floating bits is refused instead of exhausting the memory
+        for &bits in &[MAX_FLOATING_BITS + 1, 64] {
+            let program = format!("mask = {}\nmem[0] = 1", "X".repeat(bits as usize));
+            let ops = parse_program(&program, bits).unwrap();
+            let mut machine = Machine::new(AddressDecoder, bits).unwrap();
+            assert_eq!(
+                machine.run(&ops).err(),
+                Some(MachineError::TooManyFloatingBits(bits))
+            );
+            assert!(machine.memory().is_empty());
+        }
+
+        let ops = parse_program(
+            &format!("mask = {}\nmem[0] = 1\nmem[1] = 1", "1".repeat(128)),
+            128,
+        )
+        .unwrap();
+        let mut machine = Machine::new(ValueDecoder, 128).unwrap();
+        assert_eq!(machine.run(&ops).unwrap().sum(), None);
+    }
+}
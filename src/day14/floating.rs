@@ -0,0 +1,222 @@
+use super::{MachineError, Mask, Operation};
+
+/// A set of addresses, every bit is either fixed or floating and takes both values.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct Pattern {
+    /// values of the fixed bits, floating bits are 0
+    pub fixed: u128,
+    pub floating: u128,
+}
+
+impl Pattern {
+    /// the addresses a version 2 decoder writes to for `address`
+    pub fn decode(mask: &Mask, address: u128) -> Self {
+        Pattern {
+            fixed: (address | mask.ones()) & !mask.floating(),
+            floating: mask.floating(),
+        }
+    }
+
+    pub fn contains(&self, address: u128) -> bool {
+        address & !self.floating == self.fixed
+    }
+
+    /// number of addresses, `None` if all 2^128 addresses are in the pattern
+    pub fn size(&self) -> Option<u128> {
+        1u128.checked_shl(self.floating.count_ones())
+    }
+
+    /// Whether both patterns share an address, i.e. no bit is fixed differently.
+    pub fn overlaps(&self, other: &Pattern) -> bool {
+        let both_fixed = !self.floating & !other.floating;
+        (self.fixed ^ other.fixed) & both_fixed == 0
+    }
+
+    /// Disjoint patterns covering the addresses of `self` that are not in `other`.
+    ///
+    /// Every bit that floats here but is fixed in `other` splits off the part where the bit
+    /// differs from `other`, the rest continues with the bit fixed like in `other`.
+    pub fn subtract(&self, other: &Pattern) -> Vec<Pattern> {
+        if !self.overlaps(other) {
+            return vec![*self];
+        }
+        let mut parts = Vec::new();
+        let mut rest = *self;
+        let mut split = self.floating & !other.floating;
+        while split != 0 {
+            let bit = split & split.wrapping_neg();
+            rest.floating &= !bit;
+            parts.push(Pattern {
+                fixed: rest.fixed | (!other.fixed & bit),
+                floating: rest.floating,
+            });
+            rest.fixed |= other.fixed & bit;
+            split &= !bit;
+        }
+        // what is left lies completely inside `other`
+        parts
+    }
+}
+
+/// Memory of a version 2 decoder that stores every write as one pattern instead of
+/// expanding it into `2^n` addresses.
+///
+/// The stored patterns are disjoint, a new write removes its addresses from all earlier ones.
+#[derive(Clone, Debug, Default)]
+pub struct FloatingMemory {
+    writes: Vec<(Pattern, u128)>,
+}
+
+impl FloatingMemory {
+    pub fn new() -> Self {
+        FloatingMemory::default()
+    }
+
+    pub fn write(&mut self, pattern: Pattern, value: u128) {
+        self.writes = self
+            .writes
+            .iter()
+            .flat_map(|(earlier, v)| earlier.subtract(&pattern).into_iter().map(move |p| (p, *v)))
+            .filter(|(_, v)| *v != 0)
+            .collect();
+        if value != 0 {
+            self.writes.push((pattern, value));
+        }
+    }
+
+    /// Executes a program with version 2 semantics, fails for an unsupported width.
+    pub fn run(&mut self, ops: &[Operation], width: u32) -> Result<&mut Self, MachineError> {
+        let mut mask = Mask::zeros(width).ok_or(MachineError::UnsupportedWidth(width))?;
+        for op in ops {
+            match *op {
+                Operation::UpdateMask(m) => mask = m,
+                Operation::WriteValue { address, value } => {
+                    self.write(Pattern::decode(&mask, address), value)
+                }
+            }
+        }
+        Ok(self)
+    }
+
+    pub fn get(&self, address: u128) -> u128 {
+        self.writes
+            .iter()
+            .find(|(pattern, _)| pattern.contains(address))
+            .map_or(0, |(_, value)| *value)
+    }
+
+    /// Disjoint patterns with their values, addresses that were never written or hold 0
+    /// are left out.
+    pub fn patterns(&self) -> &[(Pattern, u128)] {
+        &self.writes
+    }
+
+    /// Sum of all values in memory, `None` if it does not fit into a `u128`.
+    pub fn sum(&self) -> Option<u128> {
+        self.writes.iter().try_fold(0u128, |sum, (pattern, value)| {
+            sum.checked_add(pattern.size()?.checked_mul(*value)?)
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::day14::decoder::{floating_addresses, AddressDecoder};
+    use crate::day14::{parse_program, Machine};
+    use rand::Rng;
+
+    fn pattern(s: &str) -> Pattern {
+        let mask = Mask::parse(s).unwrap();
+        Pattern {
+            fixed: mask.ones(),
+            floating: mask.floating(),
+        }
+    }
+
+    fn addresses(patterns: &[Pattern]) -> Vec<u128> {
+        let mut addresses = patterns
+            .iter()
+            .flat_map(|p| floating_addresses(p.fixed, p.floating).unwrap())
+            .collect::<Vec<u128>>();
+        addresses.sort_unstable();
+        addresses
+    }
+
+    #[test]
+    fn subtraction() {
+        assert_eq!(pattern("1X").subtract(&pattern("0X")), vec![pattern("1X")]);
+        assert!(pattern("1X").subtract(&pattern("XX")).is_empty());
+
+        let parts = pattern("XXX").subtract(&pattern("1X0"));
+        assert_eq!(parts.len(), 2);
+        assert!(!parts[0].overlaps(&parts[1]));
+        assert_eq!(
+            addresses(&parts),
+            vec![0b000, 0b001, 0b010, 0b011, 0b101, 0b111]
+        );
+    }
+
+    #[test]
+    fn part2_example() {
+        let input = "mask = 000000000000000000000000000000X1001X
+mem[42] = 100
+mask = 00000000000000000000000000000000X0XX
+mem[26] = 1";
+        let ops = parse_program(input, 36).unwrap();
+        let mut memory = FloatingMemory::new();
+        assert_eq!(memory.run(&ops, 36).unwrap().sum(), Some(208));
+        assert_eq!(memory.get(59), 100);
+        assert_eq!(memory.get(16), 1);
+        assert_eq!(memory.get(0), 0);
+    }
+
+    #[test]
+    fn many_floating_bits() {
+        let mut memory = FloatingMemory::new();
+        memory.write(pattern(&"X".repeat(36)), 3);
+        memory.write(pattern(&format!("1{}", "X".repeat(35))), 1);
+        assert_eq!(memory.sum(), Some((1 << 35) * 3 + (1 << 35)));
+
+        memory.write(pattern(&"X".repeat(128)), 1);
+        assert_eq!(memory.patterns().len(), 1);
+        assert_eq!(memory.sum(), None);
+    }
+
+    #[test]
+    fn matches_expanded_addresses() {
+        let mut rng = rand::thread_rng();
+        for _ in 0..200 {
+            let width = rng.gen_range(1, 9);
+            let mut program = String::new();
+            for _ in 0..rng.gen_range(1, 12) {
+                if rng.gen_range(0, 3) == 0 {
+                    let mask = (0..width)
+                        .map(|_| ['0', '1', 'X', 'X'][rng.gen_range(0, 4)])
+                        .collect::<String>();
+                    program += &format!("mask = {}\n", mask);
+                } else {
+                    program += &format!(
+                        "mem[{}] = {}\n",
+                        rng.gen_range(0, 1 << width),
+                        rng.gen_range(0, (1 << width).min(10))
+                    );
+                }
+            }
+            let ops = parse_program(&program, width).unwrap();
+
+            let mut machine = Machine::new(AddressDecoder, width).unwrap();
+            let mut memory = FloatingMemory::new();
+            memory.run(&ops, width).unwrap();
+            assert_eq!(
+                memory.sum(),
+                machine.run(&ops).unwrap().sum(),
+                "{}",
+                program
+            );
+            for (address, value) in machine.memory() {
+                assert_eq!(memory.get(*address), *value, "{}", program);
+            }
+        }
+    }
+}
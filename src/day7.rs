@@ -1,21 +1,17 @@
+use crate::graph::{Cycle, Graph};
 use aoc_runner_derive::{aoc, aoc_generator};
 use lazy_static::lazy_static;
 use regex::Regex;
-use std::collections::{HashMap, HashSet};
 
 struct BagRules {
-    /// all the bags
-    bags: HashMap<String, Bag>,
-
-    /// which bag is inside of which bags?
-    contained_by_lookup: HashMap<String, Vec<(usize, String)>>,
+    /// edges go from the outer to the inner bags, weighted by quantity
+    graph: Graph<String, usize>,
 }
 
 impl BagRules {
     fn new() -> Self {
         BagRules {
-            bags: HashMap::new(),
-            contained_by_lookup: HashMap::new(),
+            graph: Graph::new(),
         }
     }
 }
@@ -81,13 +77,9 @@ fn parse_input_day7(input: &str) -> BagRules {
         .lines()
         .filter_map(|line| parse_bag_rule(line))
         .fold(BagRules::new(), |mut rules, bag| {
-            rules.bags.insert(bag.color.clone(), bag.clone());
+            rules.graph.add_node(bag.color.clone());
             for (quantity, color) in bag.inner_bags {
-                if !rules.contained_by_lookup.contains_key(&color) {
-                    rules.contained_by_lookup.insert(color.clone(), Vec::new());
-                }
-                let lookup = rules.contained_by_lookup.get_mut(&color).unwrap();
-                lookup.push((quantity, bag.color.clone()));
+                rules.graph.add_edge(bag.color.clone(), color, quantity);
             }
             rules
         })
@@ -95,63 +87,28 @@ fn parse_input_day7(input: &str) -> BagRules {
 
 #[aoc(day7, part1)]
 fn part1(rules: &BagRules) -> usize {
-    let target = "shiny gold";
-
-    if !rules.bags.contains_key(target) {
-        return 0;
-    }
-
-    let mut stack: Vec<&(usize, String)> = Vec::new();
-    let mut visited: HashSet<&String> = HashSet::new();
-    let mut counter = 0;
-
-    // start at target and go up the tree
-    rules
-        .contained_by_lookup
-        .get(target)
-        .unwrap()
-        .iter()
-        .for_each(|item| stack.push(item));
-
-    while let Some((_cur_count, cur_bag)) = stack.pop() {
-        if visited.contains(cur_bag) {
-            continue;
-        }
-        visited.insert(cur_bag);
-        counter += 1;
+    let target = "shiny gold".to_string();
 
-        if let Some(inner_bags) = rules.contained_by_lookup.get(cur_bag) {
-            for inner_bag in inner_bags {
-                stack.push(inner_bag);
-            }
-        }
-    }
+    // every bag that can reach the target eventually contains it
+    rules.graph.reaching(&target).len()
+}
 
-    counter
+/// Number of bags inside of a `color` bag, fails if one of them would have to contain itself.
+fn count_inner_bags(rules: &BagRules, color: &str) -> Result<usize, Cycle<String>> {
+    // number of bags inside of every bag the target contains, starting with the empty ones
+    let inner_bags = rules.graph.fold_from(&color.to_string(), |_, inner| {
+        inner
+            .iter()
+            .map(|(_, quantity, count)| **quantity * (1 + **count))
+            .sum::<usize>()
+    })?;
+
+    Ok(inner_bags.unwrap_or(0))
 }
 
 #[aoc(day7, part2)]
 fn part2(rules: &BagRules) -> usize {
-    let target = "shiny gold";
-
-    if !rules.bags.contains_key(target) {
-        return 0;
-    }
-
-    let mut stack: Vec<(usize, String)> = Vec::new();
-    let mut counter = 0;
-
-    // start at target and go up the tree
-    stack.push((1 as usize, target.to_string()));
-
-    while let Some((cur_count, cur_bag)) = stack.pop() {
-        for inner_bag in &rules.bags.get(cur_bag.as_str()).unwrap().inner_bags {
-            counter += cur_count * inner_bag.0;
-            stack.push((cur_count * inner_bag.0, inner_bag.1.clone()));
-        }
-    }
-
-    counter
+    count_inner_bags(rules, "shiny gold").expect("a bag inside the shiny gold bag contains itself")
 }
 
 #[cfg(test)]
@@ -197,4 +154,22 @@ dark blue bags contain 2 dark violet bags.
 dark violet bags contain no other bags.";
         assert_eq!(part2(&parse_input_day7(input)), 126);
     }
+
+    #[test]
+    fn inner_bags_with_cycles() {
+        // the cycle between the red and orange bags does not concern the shiny gold bag
+        let input = "shiny gold bags contain 2 dark blue bags.
+dark blue bags contain no other bags.
+dark red bags contain 1 dark orange bag.
+dark orange bags contain 1 dark red bag.";
+        assert_eq!(
+            count_inner_bags(&parse_input_day7(input), "shiny gold"),
+            Ok(2)
+        );
+
+        let input = "shiny gold bags contain 2 dark blue bags.
+dark blue bags contain 1 shiny gold bag.";
+        assert!(count_inner_bags(&parse_input_day7(input), "shiny gold").is_err());
+        assert_eq!(count_inner_bags(&parse_input_day7(""), "shiny gold"), Ok(0));
+    }
 }
@@ -1,149 +1,440 @@
 use aoc_runner_derive::{aoc, aoc_generator};
+use core::fmt;
+use instruction_set::{Flow, InstructionSet};
 use std::collections::HashSet;
+use std::fmt::{Display, Formatter, Write};
+use std::rc::Rc;
 use std::str::FromStr;
+use trace::{Trace, TraceEntry};
 
-#[derive(Copy, Clone, Debug, PartialEq)]
-enum OpCode {
+pub mod analysis;
+pub mod assembler;
+pub mod debugger;
+pub mod diff;
+pub mod instruction_set;
+pub mod trace;
+
+/// registers `a` to `z`, `a` is the accumulator
+pub const REGISTERS: usize = 26;
+pub type Registers = [isize; REGISTERS];
+
+fn parse_register(s: &str) -> Option<usize> {
+    match s.as_bytes() {
+        [c @ b'a'..=b'z'] => Some((c - b'a') as usize),
+        _ => None,
+    }
+}
+
+fn register_name(register: usize) -> char {
+    (b'a' + register as u8) as char
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum OpCode {
     Nop,
     Acc,
     Jmp,
+    Mul,
+    /// jump if the register is greater than zero
+    Jgz,
+    /// jump if the register is less than zero
+    Jlz,
+    /// jump if the register is zero
+    Jez,
+    Out,
+    Halt,
+    /// registered in an [`InstructionSet`], which interns the name
+    Custom(&'static str),
 }
 
-impl FromStr for OpCode {
-    type Err = ();
-
-    fn from_str(s: &str) -> Result<Self, Self::Err> {
-        Ok(match s {
+impl OpCode {
+    fn builtin(s: &str) -> Option<Self> {
+        Some(match s {
             "nop" => OpCode::Nop,
             "acc" => OpCode::Acc,
             "jmp" => OpCode::Jmp,
-            _ => return Err(()),
+            "mul" => OpCode::Mul,
+            "jgz" => OpCode::Jgz,
+            "jlz" => OpCode::Jlz,
+            "jez" => OpCode::Jez,
+            "out" => OpCode::Out,
+            "halt" => OpCode::Halt,
+            _ => return None,
         })
     }
 }
 
-#[derive(Copy, Clone, Debug)]
-struct Instruction {
-    opcode: OpCode,
-    operand: isize,
+impl FromStr for OpCode {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        OpCode::builtin(s).ok_or(())
+    }
+}
+
+impl Display for OpCode {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            OpCode::Nop => f.write_str("nop"),
+            OpCode::Acc => f.write_str("acc"),
+            OpCode::Jmp => f.write_str("jmp"),
+            OpCode::Mul => f.write_str("mul"),
+            OpCode::Jgz => f.write_str("jgz"),
+            OpCode::Jlz => f.write_str("jlz"),
+            OpCode::Jez => f.write_str("jez"),
+            OpCode::Out => f.write_str("out"),
+            OpCode::Halt => f.write_str("halt"),
+            OpCode::Custom(name) => f.write_str(name),
+        }
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Instruction {
+    pub opcode: OpCode,
+    pub register: usize,
+    pub operand: isize,
+}
+
+impl Display for Instruction {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        self.opcode.fmt(f)?;
+        if self.register != 0 {
+            f.write_char(' ')?;
+            f.write_char(register_name(self.register))?;
+        }
+        write!(f, " {:+}", self.operand)
+    }
 }
 
 impl FromStr for Instruction {
     type Err = ();
 
     fn from_str(s: &str) -> Result<Self, Self::Err> {
-        let parts = s.split(" ").collect::<Vec<&str>>();
-        let opcode_string = parts.get(0).map_or(Err(()), Ok)?;
-        let operand_string: &str = parts.get(1).map_or(Err(()), Ok)?;
-        Ok(Instruction {
-            opcode: OpCode::from_str(opcode_string)?,
-            operand: operand_string.parse::<isize>().map_err(|_| ())?,
-        })
+        InstructionSet::default().parse_instruction(s).ok_or(())
     }
 }
 
 #[aoc_generator(day8)]
-fn parse_input_day8(input: &str) -> Vec<Instruction> {
+pub fn parse_input_day8(input: &str) -> Vec<Instruction> {
     input
         .lines()
         .filter_map(|line| Instruction::from_str(line).map_or(None, Some))
         .collect::<Vec<Instruction>>()
 }
 
-enum TerminationReason {
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum TerminationReason {
     InvalidAddress,
     EndOfInstructions,
     InfiniteLoop,
+    /// a `halt` instruction was executed
+    Halted,
+    /// the limit set by [`LoopDetection::InstructionLimit`] was reached
+    InstructionLimit,
+    /// a custom opcode that is not part of the instruction set
+    InvalidInstruction,
+    /// `acc` or `mul` would overflow the register, it keeps its previous value
+    Overflow,
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum LoopDetection {
+    /// stop as soon as an instruction would be executed a second time
+    ExecutedSet,
+    /// stop after executing this many instructions, for programs that revisit addresses
+    InstructionLimit(usize),
 }
 
-struct VM {
-    instructions: Vec<Instruction>,
+#[derive(Clone)]
+pub struct VM {
+    /// shared with snapshots until an instruction gets patched
+    instructions: Rc<Vec<Instruction>>,
+    instruction_set: InstructionSet,
+    loop_detection: LoopDetection,
     executed: HashSet<usize>,
+    steps: usize,
+    halted: bool,
     address: usize,
-    accumulator: isize,
+    registers: Registers,
+    output: Vec<isize>,
+    trace: Option<Trace>,
 }
 
 impl VM {
-    fn from_instructions(instructions: &Vec<Instruction>) -> Self {
+    pub fn from_instructions(instructions: &Vec<Instruction>) -> Self {
+        VM::with_instruction_set(instructions, InstructionSet::default())
+    }
+
+    pub fn with_instruction_set(
+        instructions: &[Instruction],
+        instruction_set: InstructionSet,
+    ) -> Self {
         VM {
-            instructions: instructions.clone(),
+            instructions: Rc::new(instructions.to_vec()),
+            instruction_set,
+            loop_detection: LoopDetection::ExecutedSet,
             executed: HashSet::new(),
+            steps: 0,
+            halted: false,
             address: 0,
-            accumulator: 0,
+            registers: [0; REGISTERS],
+            output: Vec::new(),
+            trace: None,
         }
     }
 
-    fn reset(&mut self) {
+    pub fn reset(&mut self) {
         self.executed.clear();
+        self.steps = 0;
+        self.halted = false;
         self.address = 0;
-        self.accumulator = 0;
+        self.registers = [0; REGISTERS];
+        self.output.clear();
+        if let Some(trace) = &mut self.trace {
+            trace.clear();
+        }
+    }
+
+    /// Captures the complete execution state, including the instructions.
+    pub fn snapshot(&self) -> Snapshot {
+        Snapshot {
+            instructions: self.instructions.clone(),
+            executed: self.executed.clone(),
+            steps: self.steps,
+            halted: self.halted,
+            address: self.address,
+            registers: self.registers,
+            output: self.output.clone(),
+            trace: self.trace.clone(),
+        }
+    }
+
+    pub fn restore(&mut self, snapshot: &Snapshot) {
+        self.instructions = snapshot.instructions.clone();
+        self.executed = snapshot.executed.clone();
+        self.steps = snapshot.steps;
+        self.halted = snapshot.halted;
+        self.address = snapshot.address;
+        self.registers = snapshot.registers;
+        self.output = snapshot.output.clone();
+        self.trace = snapshot.trace.clone();
+    }
+
+    /// Replaces the instruction at `address` without affecting snapshots or clones
+    /// and returns the previous one.
+    pub fn patch(&mut self, address: usize, instruction: Instruction) -> Option<Instruction> {
+        let slot = Rc::make_mut(&mut self.instructions).get_mut(address)?;
+        Some(std::mem::replace(slot, instruction))
+    }
+
+    pub fn set_loop_detection(&mut self, loop_detection: LoopDetection) {
+        self.loop_detection = loop_detection;
+    }
+
+    /// Starts recording every executed instruction, see [`VM::trace`].
+    pub fn enable_tracing(&mut self) {
+        if self.trace.is_none() {
+            self.trace = Some(Trace::default());
+        }
+    }
+
+    pub fn trace(&self) -> Option<&Trace> {
+        self.trace.as_ref()
+    }
+
+    pub fn instructions(&self) -> &[Instruction] {
+        &self.instructions
+    }
+
+    /// addresses of all instructions that have been executed since the last reset
+    pub fn executed(&self) -> &HashSet<usize> {
+        &self.executed
+    }
+
+    /// number of instructions executed since the last reset
+    pub fn steps(&self) -> usize {
+        self.steps
+    }
+
+    pub fn address(&self) -> usize {
+        self.address
+    }
+
+    pub fn accumulator(&self) -> isize {
+        self.registers[0]
+    }
+
+    pub fn registers(&self) -> &Registers {
+        &self.registers
+    }
+
+    /// values written by `out` instructions
+    pub fn output(&self) -> &[isize] {
+        &self.output
     }
 
-    fn run(&mut self) -> TerminationReason {
-        while let Some(instruction) = self.instructions.get(self.address) {
-            if self.executed.contains(&self.address) {
-                return TerminationReason::InfiniteLoop;
+    /// Executes the instruction at the current address.
+    /// Returns the reason if the VM cannot continue, in which case nothing was executed.
+    pub fn step(&mut self) -> Option<TerminationReason> {
+        if self.halted {
+            return Some(TerminationReason::Halted);
+        }
+        let instruction = match self.instructions.get(self.address) {
+            Some(instruction) => *instruction,
+            None if self.address == self.instructions.len() => {
+                return Some(TerminationReason::EndOfInstructions)
             }
-            self.executed.insert(self.address);
+            None => return Some(TerminationReason::InvalidAddress),
+        };
+        match self.loop_detection {
+            LoopDetection::ExecutedSet if self.executed.contains(&self.address) => {
+                return Some(TerminationReason::InfiniteLoop);
+            }
+            LoopDetection::InstructionLimit(limit) if self.steps >= limit => {
+                return Some(TerminationReason::InstructionLimit);
+            }
+            _ => {}
+        }
 
-            match instruction.opcode {
-                OpCode::Nop => {}
-                OpCode::Acc => {
-                    self.accumulator += instruction.operand;
+        let address = self.address;
+        let register = instruction.register;
+        let before = self.registers[register];
+        let flow = match instruction.opcode {
+            OpCode::Nop => Flow::Next,
+            OpCode::Acc => match before.checked_add(instruction.operand) {
+                Some(value) => {
+                    self.registers[register] = value;
+                    Flow::Next
                 }
-                OpCode::Jmp => {
-                    self.address = (self.address as isize + instruction.operand - 1) as usize;
+                None => return Some(TerminationReason::Overflow),
+            },
+            OpCode::Mul => match before.checked_mul(instruction.operand) {
+                Some(value) => {
+                    self.registers[register] = value;
+                    Flow::Next
                 }
+                None => return Some(TerminationReason::Overflow),
+            },
+            OpCode::Jmp => Flow::Jump(instruction.operand),
+            OpCode::Jgz if self.registers[register] > 0 => Flow::Jump(instruction.operand),
+            OpCode::Jlz if self.registers[register] < 0 => Flow::Jump(instruction.operand),
+            OpCode::Jez if self.registers[register] == 0 => Flow::Jump(instruction.operand),
+            OpCode::Jgz | OpCode::Jlz | OpCode::Jez => Flow::Next,
+            OpCode::Out => Flow::Output(self.registers[register]),
+            OpCode::Halt => Flow::Halt,
+            OpCode::Custom(name) => match self.instruction_set.handler(name) {
+                Some(handler) => handler(&mut self.registers, &instruction),
+                None => return Some(TerminationReason::InvalidInstruction),
+            },
+        };
+        self.executed.insert(address);
+        self.steps += 1;
+
+        match flow {
+            Flow::Next => self.address += 1,
+            Flow::Jump(offset) => {
+                // jumping before the first instruction ends up at an address that never exists
+                self.address = self
+                    .address
+                    .checked_add_signed(offset)
+                    .unwrap_or(usize::MAX);
+            }
+            Flow::Output(value) => {
+                self.output.push(value);
+                self.address += 1;
             }
+            Flow::Halt => self.halted = true,
+        }
 
-            self.address += 1;
+        if let Some(trace) = &mut self.trace {
+            trace.record(TraceEntry {
+                address,
+                opcode: instruction.opcode,
+                register,
+                operand: instruction.operand,
+                before,
+                after: self.registers[register],
+            });
         }
+        None
+    }
 
-        if self.address == self.instructions.len() {
-            TerminationReason::EndOfInstructions
-        } else {
-            TerminationReason::InvalidAddress
+    pub fn run(&mut self) -> TerminationReason {
+        loop {
+            if let Some(reason) = self.step() {
+                return reason;
+            }
         }
     }
 }
 
+/// The complete execution state of a [`VM`], see [`VM::snapshot`].
+#[derive(Clone)]
+pub struct Snapshot {
+    instructions: Rc<Vec<Instruction>>,
+    executed: HashSet<usize>,
+    steps: usize,
+    halted: bool,
+    address: usize,
+    registers: Registers,
+    output: Vec<isize>,
+    trace: Option<Trace>,
+}
+
 #[aoc(day8, part1)]
 fn part1(input: &Vec<Instruction>) -> isize {
     let mut vm = VM::from_instructions(input);
     vm.run();
-    vm.accumulator
+    vm.accumulator()
 }
 
 #[aoc(day8, part2)]
 fn part2(input: &Vec<Instruction>) -> Option<isize> {
     let mut vm = VM::from_instructions(input);
+    let initial = vm.snapshot();
 
-    for i in 0..vm.instructions.len() {
-        let instruction = vm.instructions.get_mut(i).unwrap();
-        let original_opcode = instruction.opcode;
-        match original_opcode {
-            OpCode::Jmp => instruction.opcode = OpCode::Nop,
-            OpCode::Nop => instruction.opcode = OpCode::Jmp,
-            _ => {}
-        }
+    for (i, instruction) in input.iter().enumerate() {
+        let opcode = match instruction.opcode {
+            OpCode::Jmp => OpCode::Nop,
+            OpCode::Nop => OpCode::Jmp,
+            _ => continue,
+        };
+        vm.restore(&initial);
+        vm.patch(
+            i,
+            Instruction {
+                opcode,
+                ..*instruction
+            },
+        );
 
-        let reason = vm.run();
-        match reason {
-            TerminationReason::EndOfInstructions => return Some(vm.accumulator),
-            _ => vm.reset(),
+        if vm.run() == TerminationReason::EndOfInstructions {
+            return Some(vm.accumulator());
         }
-
-        let instruction = vm.instructions.get_mut(i).unwrap();
-        instruction.opcode = original_opcode;
     }
     None
 }
 
-#[cfg(test)]
-mod tests {
-    use super::*;
+#[aoc(day8, part2, control_flow)]
+fn part2_control_flow(input: &[Instruction]) -> Option<isize> {
+    let fix = match analysis::find_repair(input) {
+        analysis::Repair::Unique(fix) => fix,
+        _ => return None,
+    };
+    let mut instructions = input.to_vec();
+    fix.apply(&mut instructions);
 
-    const INPUT: &str = "nop +0
+    let mut vm = VM::from_instructions(&instructions);
+    match vm.run() {
+        TerminationReason::EndOfInstructions => Some(vm.accumulator()),
+        _ => None,
+    }
+}
+
+/// the example program of the puzzle, shared by the tests of all submodules
+#[cfg(test)]
+pub(crate) const EXAMPLE: &str = "nop +0
 acc +1
 jmp +4
 acc +3
@@ -153,13 +444,95 @@ acc +1
 jmp -4
 acc +6";
 
+#[cfg(test)]
+mod tests {
+    use super::*;
+
     #[test]
     fn part1_example() {
-        assert_eq!(part1(&parse_input_day8(INPUT)), 5);
+        assert_eq!(part1(&parse_input_day8(EXAMPLE)), 5);
     }
 
     #[test]
     fn part2_example() {
-        assert_eq!(part2(&mut parse_input_day8(INPUT)), Some(8));
+        assert_eq!(part2(&mut parse_input_day8(EXAMPLE)), Some(8));
+    }
+
+    #[test]
+    fn part2_control_flow_example() {
+        assert_eq!(part2_control_flow(&parse_input_day8(EXAMPLE)), Some(8));
+    }
+
+    #[test]
+    fn run_reports_termination_reason() {
+        let mut vm = VM::from_instructions(&parse_input_day8("acc +1\njmp +1"));
+        assert_eq!(vm.run(), TerminationReason::EndOfInstructions);
+        let mut vm = VM::from_instructions(&parse_input_day8("acc +1\njmp -2"));
+        assert_eq!(vm.run(), TerminationReason::InvalidAddress);
+        let mut vm = VM::from_instructions(&parse_input_day8("jmp +0"));
+        assert_eq!(vm.run(), TerminationReason::InfiniteLoop);
+    }
+
+    #[test]
+    fn registers_and_conditional_jumps() {
+        // multiplies b by 2 until it exceeds 100, counting the rounds in a
+        let program = parse_input_day8(
+            "acc b +1
+mul b 2
+acc +1
+acc b -100
+jgz b +3
+acc b +100
+jmp -5
+acc b +100
+out b
+halt",
+        );
+        let mut vm = VM::from_instructions(&program);
+        vm.set_loop_detection(LoopDetection::InstructionLimit(1000));
+        assert_eq!(vm.run(), TerminationReason::Halted);
+        assert_eq!(vm.accumulator(), 7);
+        assert_eq!(vm.output(), &[128]);
+    }
+
+    #[test]
+    fn snapshot_and_restore() {
+        let mut vm = VM::from_instructions(&parse_input_day8(EXAMPLE));
+        for _ in 0..3 {
+            vm.step();
+        }
+        let snapshot = vm.snapshot();
+        let mut patched = vm.clone();
+        patched.patch(7, parse_input_day8("nop -4")[0]);
+
+        assert_eq!(vm.run(), TerminationReason::InfiniteLoop);
+        assert_eq!(patched.run(), TerminationReason::EndOfInstructions);
+        assert_eq!(patched.accumulator(), 8);
+
+        vm.restore(&snapshot);
+        assert_eq!(vm.address(), 6);
+        assert_eq!(vm.accumulator(), 1);
+        assert_eq!(vm.executed().len(), 3);
+        assert_eq!(vm.instructions()[7].opcode, OpCode::Jmp);
+        assert_eq!(vm.run(), TerminationReason::InfiniteLoop);
+        assert_eq!(vm.accumulator(), 5);
+    }
+
+    #[test]
+    fn instruction_limit() {
+        let mut vm = VM::from_instructions(&parse_input_day8("acc +1\njmp -1"));
+        vm.set_loop_detection(LoopDetection::InstructionLimit(11));
+        assert_eq!(vm.run(), TerminationReason::InstructionLimit);
+        assert_eq!(vm.steps(), 11);
+        assert_eq!(vm.accumulator(), 6);
+    }
+
+    #[test]
+    fn overflow() {
+        let mut vm = VM::from_instructions(&parse_input_day8("acc +2\nmul +1000000\njmp -1"));
+        vm.set_loop_detection(LoopDetection::InstructionLimit(100));
+        assert_eq!(vm.run(), TerminationReason::Overflow);
+        assert_eq!(vm.accumulator(), 2_000_000_000_000_000_000);
+        assert_eq!(vm.address(), 1);
     }
 }
@@ -0,0 +1,222 @@
+use super::{Instruction, OpCode};
+
+/// Address of the instruction executed after the one at `address`, `None` if it jumps out
+/// of the program. Fails for instructions whose successor depends on the registers.
+fn successor(
+    address: usize,
+    end: usize,
+    opcode: OpCode,
+    operand: isize,
+) -> Result<Option<usize>, ()> {
+    let next = match opcode {
+        OpCode::Nop | OpCode::Acc | OpCode::Mul | OpCode::Out => Some(address + 1),
+        OpCode::Jmp => address.checked_add_signed(operand),
+        // halting terminates the program just like running past the last instruction
+        OpCode::Halt => Some(end),
+        OpCode::Jgz | OpCode::Jlz | OpCode::Jez | OpCode::Custom(_) => return Err(()),
+    };
+    Ok(next.filter(|next| *next <= end))
+}
+
+fn flipped(opcode: OpCode) -> Option<OpCode> {
+    match opcode {
+        OpCode::Nop => Some(OpCode::Jmp),
+        OpCode::Jmp => Some(OpCode::Nop),
+        _ => None,
+    }
+}
+
+/// Control-flow graph of a program. Every instruction has at most one successor,
+/// the end of the program is the extra node `instructions.len()`.
+pub struct ControlFlowGraph {
+    successors: Vec<Option<usize>>,
+    predecessors: Vec<Vec<usize>>,
+}
+
+impl ControlFlowGraph {
+    /// Fails with the address of the first conditional or custom instruction,
+    /// as their successors are not known statically.
+    pub fn new(instructions: &[Instruction]) -> Result<Self, usize> {
+        let end = instructions.len();
+        let mut successors = Vec::with_capacity(end);
+        let mut predecessors = vec![Vec::new(); end + 1];
+        for (address, instruction) in instructions.iter().enumerate() {
+            let next = successor(address, end, instruction.opcode, instruction.operand)
+                .map_err(|_| address)?;
+            if let Some(next) = next {
+                predecessors[next].push(address);
+            }
+            successors.push(next);
+        }
+        Ok(ControlFlowGraph {
+            successors,
+            predecessors,
+        })
+    }
+
+    fn end(&self) -> usize {
+        self.successors.len()
+    }
+
+    pub fn successor(&self, address: usize) -> Option<usize> {
+        *self.successors.get(address)?
+    }
+
+    /// For every node, whether execution starting there reaches the end of the program.
+    pub fn reaches_end(&self) -> Vec<bool> {
+        let mut reaches = vec![false; self.end() + 1];
+        let mut stack = vec![self.end()];
+        reaches[self.end()] = true;
+        while let Some(node) = stack.pop() {
+            for predecessor in &self.predecessors[node] {
+                if !reaches[*predecessor] {
+                    reaches[*predecessor] = true;
+                    stack.push(*predecessor);
+                }
+            }
+        }
+        reaches
+    }
+
+    /// Addresses executed when starting at address 0, in order of execution,
+    /// until the program leaves the graph or an address is about to be executed again.
+    pub fn execution_path(&self) -> Vec<usize> {
+        let mut visited = vec![false; self.end() + 1];
+        let mut path = Vec::new();
+        let mut address = 0;
+        while address < self.end() && !visited[address] {
+            visited[address] = true;
+            path.push(address);
+            match self.successors[address] {
+                Some(next) => address = next,
+                None => break,
+            }
+        }
+        path
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Fix {
+    pub address: usize,
+    pub opcode: OpCode,
+}
+
+impl Fix {
+    pub fn apply(&self, instructions: &mut [Instruction]) {
+        instructions[self.address].opcode = self.opcode;
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Repair {
+    /// the program already terminates
+    NotNeeded,
+    /// exactly one flipped instruction makes the program terminate
+    Unique(Fix),
+    /// no single flipped instruction makes the program terminate
+    Impossible,
+    /// several different flips make the program terminate
+    Ambiguous(Vec<Fix>),
+    /// the instruction at this address cannot be analyzed statically
+    Unsupported(usize),
+}
+
+/// Finds all single `jmp`/`nop` flips that make the program terminate in O(n).
+///
+/// Only instructions on the original execution path matter, and since the original
+/// program does not terminate, the flipped instruction is not visited again afterwards.
+/// So a flip works exactly if its new successor reaches the end in the unmodified graph.
+pub fn find_repair(instructions: &[Instruction]) -> Repair {
+    let graph = match ControlFlowGraph::new(instructions) {
+        Ok(graph) => graph,
+        Err(address) => return Repair::Unsupported(address),
+    };
+    let reaches_end = graph.reaches_end();
+    if reaches_end[0] {
+        return Repair::NotNeeded;
+    }
+
+    let mut fixes = graph
+        .execution_path()
+        .into_iter()
+        .filter_map(|address| {
+            let instruction = instructions[address];
+            let opcode = flipped(instruction.opcode)?;
+            let next =
+                successor(address, instructions.len(), opcode, instruction.operand).ok()??;
+            if *reaches_end.get(next)? {
+                Some(Fix { address, opcode })
+            } else {
+                None
+            }
+        })
+        .collect::<Vec<Fix>>();
+
+    match fixes.len() {
+        0 => Repair::Impossible,
+        1 => Repair::Unique(fixes.remove(0)),
+        _ => Repair::Ambiguous(fixes),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::day8::{parse_input_day8, EXAMPLE};
+
+    #[test]
+    fn reaches_end() {
+        let graph = ControlFlowGraph::new(&parse_input_day8(EXAMPLE)).unwrap();
+        assert_eq!(
+            graph.reaches_end(),
+            vec![false, false, false, false, false, false, false, false, true, true]
+        );
+        assert_eq!(graph.execution_path(), vec![0, 1, 2, 6, 7, 3, 4]);
+    }
+
+    #[test]
+    fn unique_repair() {
+        assert_eq!(
+            find_repair(&parse_input_day8(EXAMPLE)),
+            Repair::Unique(Fix {
+                address: 7,
+                opcode: OpCode::Nop
+            })
+        );
+    }
+
+    #[test]
+    fn no_repair_needed_or_possible() {
+        assert_eq!(
+            find_repair(&parse_input_day8("acc +1\nnop +5")),
+            Repair::NotNeeded
+        );
+        assert_eq!(
+            find_repair(&parse_input_day8("nop +0\njmp -1\njmp -2")),
+            Repair::Impossible
+        );
+        assert_eq!(
+            find_repair(&parse_input_day8("acc +1\njez +2\njmp +0")),
+            Repair::Unsupported(1)
+        );
+    }
+
+    #[test]
+    fn ambiguous_repair() {
+        let instructions = parse_input_day8("nop +2\njmp +0\nacc +1");
+        assert_eq!(
+            find_repair(&instructions),
+            Repair::Ambiguous(vec![
+                Fix {
+                    address: 0,
+                    opcode: OpCode::Jmp
+                },
+                Fix {
+                    address: 1,
+                    opcode: OpCode::Nop
+                },
+            ])
+        );
+    }
+}
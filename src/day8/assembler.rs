@@ -0,0 +1,250 @@
+use super::instruction_set::{strip_comment, InstructionSet};
+use super::{parse_register, register_name, Instruction, OpCode};
+use core::fmt;
+use std::collections::{BTreeSet, HashMap};
+use std::fmt::{Display, Formatter, Write};
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum AssemblyErrorKind {
+    UnknownOpCode(String),
+    InvalidOperand(String),
+    TooManyOperands,
+    /// labels must be identifiers and single letters are reserved for registers
+    InvalidLabel(String),
+    DuplicateLabel(String),
+    UnknownLabel(String),
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct AssemblyError {
+    /// 1-based line number in the source
+    pub line: usize,
+    pub kind: AssemblyErrorKind,
+}
+
+impl Display for AssemblyError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "line {}: ", self.line)?;
+        match &self.kind {
+            AssemblyErrorKind::UnknownOpCode(s) => write!(f, "unknown opcode `{}`", s),
+            AssemblyErrorKind::InvalidOperand(s) => write!(f, "invalid operand `{}`", s),
+            AssemblyErrorKind::TooManyOperands => f.write_str("too many operands"),
+            AssemblyErrorKind::InvalidLabel(s) => write!(f, "invalid label `{}`", s),
+            AssemblyErrorKind::DuplicateLabel(s) => write!(f, "duplicate label `{}`", s),
+            AssemblyErrorKind::UnknownLabel(s) => write!(f, "unknown label `{}`", s),
+        }
+    }
+}
+
+fn is_label(s: &str) -> bool {
+    let mut chars = s.chars();
+    let valid = match chars.next() {
+        Some(c) => c.is_ascii_alphabetic() || c == '_',
+        None => false,
+    };
+    valid && chars.all(|c| c.is_ascii_alphanumeric() || c == '_') && parse_register(s).is_none()
+}
+
+/// A source line containing an instruction, with labels and comments removed.
+struct SourceInstruction<'a> {
+    line: usize,
+    text: &'a str,
+}
+
+/// Assembles source code with labels into instructions.
+///
+/// Every line may start with any number of `label:` definitions followed by an instruction
+/// `<opcode> [register] [operand]`. The operand may be a label, which is replaced by the
+/// offset from the instruction to the labelled address. Anything after `;` or `#` is a comment.
+pub fn assemble(
+    source: &str,
+    instruction_set: &InstructionSet,
+) -> Result<Vec<Instruction>, AssemblyError> {
+    // first pass: collect the label addresses
+    let mut labels = HashMap::new();
+    let mut source_instructions = Vec::new();
+    for (i, line) in source.lines().enumerate() {
+        let mut text = strip_comment(line).trim();
+        while let Some(colon) = text.find(':') {
+            let label = text[..colon].trim();
+            let error = |kind| AssemblyError { line: i + 1, kind };
+            if !is_label(label) {
+                return Err(error(AssemblyErrorKind::InvalidLabel(label.to_string())));
+            }
+            if labels.insert(label, source_instructions.len()).is_some() {
+                return Err(error(AssemblyErrorKind::DuplicateLabel(label.to_string())));
+            }
+            text = text[(colon + 1)..].trim();
+        }
+        if !text.is_empty() {
+            source_instructions.push(SourceInstruction { line: i + 1, text });
+        }
+    }
+
+    // second pass: parse the instructions and resolve the labels
+    source_instructions
+        .iter()
+        .enumerate()
+        .map(|(address, source)| {
+            let error = |kind| AssemblyError {
+                line: source.line,
+                kind,
+            };
+            let mut parts = source.text.split_whitespace();
+            let opcode_string = parts.next().unwrap();
+            let opcode = instruction_set
+                .opcode(opcode_string)
+                .ok_or_else(|| error(AssemblyErrorKind::UnknownOpCode(opcode_string.into())))?;
+
+            let operands = parts.collect::<Vec<&str>>();
+            let (register, operand) = match operands.as_slice() {
+                [] => (0, None),
+                [r] if parse_register(r).is_some() => (parse_register(r).unwrap(), None),
+                [o] => (0, Some(*o)),
+                [r, o] => match parse_register(r) {
+                    Some(register) => (register, Some(*o)),
+                    None => return Err(error(AssemblyErrorKind::InvalidOperand(r.to_string()))),
+                },
+                _ => return Err(error(AssemblyErrorKind::TooManyOperands)),
+            };
+            let operand = match operand {
+                None => 0,
+                Some(o) if is_label(o) => match labels.get(o) {
+                    Some(target) => *target as isize - address as isize,
+                    None => return Err(error(AssemblyErrorKind::UnknownLabel(o.to_string()))),
+                },
+                Some(o) => o
+                    .parse()
+                    .map_err(|_| error(AssemblyErrorKind::InvalidOperand(o.to_string())))?,
+            };
+
+            Ok(Instruction {
+                opcode,
+                register,
+                operand,
+            })
+        })
+        .collect()
+}
+
+/// Writes instructions in the plain format read by `parse_input_day8`.
+pub fn to_text(instructions: &[Instruction]) -> String {
+    let mut text = String::new();
+    for instruction in instructions {
+        writeln!(text, "{}", instruction).unwrap();
+    }
+    text
+}
+
+fn is_jump(opcode: OpCode) -> bool {
+    matches!(
+        opcode,
+        OpCode::Jmp | OpCode::Jgz | OpCode::Jlz | OpCode::Jez
+    )
+}
+
+fn jump_target(address: usize, instruction: &Instruction, end: usize) -> Option<usize> {
+    if !is_jump(instruction.opcode) {
+        return None;
+    }
+    address
+        .checked_add_signed(instruction.operand)
+        .filter(|target| *target <= end)
+}
+
+/// Turns instructions back into assembler source, naming every jump target `l<address>`.
+/// Jumps that leave the program keep their numeric offset.
+pub fn disassemble(instructions: &[Instruction]) -> String {
+    let end = instructions.len();
+    let targets = instructions
+        .iter()
+        .enumerate()
+        .filter_map(|(address, instruction)| jump_target(address, instruction, end))
+        .collect::<BTreeSet<usize>>();
+
+    let mut source = String::new();
+    for (address, instruction) in instructions.iter().enumerate() {
+        if targets.contains(&address) {
+            writeln!(source, "l{}:", address).unwrap();
+        }
+        write!(source, "    {}", instruction.opcode).unwrap();
+        if instruction.register != 0 {
+            write!(source, " {}", register_name(instruction.register)).unwrap();
+        }
+        match jump_target(address, instruction, end) {
+            Some(target) => writeln!(source, " l{}", target).unwrap(),
+            None => writeln!(source, " {:+}", instruction.operand).unwrap(),
+        }
+    }
+    if targets.contains(&end) {
+        writeln!(source, "l{}:", end).unwrap();
+    }
+    source
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::day8::{parse_input_day8, EXAMPLE};
+
+    #[test]
+    fn assemble_with_labels() {
+        let source = "; the example program from day 8
+        nop +0
+again:  acc +1
+        jmp skip        ; avoid the -99
+back:   acc +3
+        jmp again
+        acc -99
+skip:   acc +1
+        jmp back
+        acc +6
+";
+        let instructions = assemble(source, &InstructionSet::default()).unwrap();
+        assert_eq!(instructions, parse_input_day8(EXAMPLE));
+        assert_eq!(to_text(&instructions), format!("{}\n", EXAMPLE));
+    }
+
+    #[test]
+    fn disassemble_round_trip() {
+        let instructions = parse_input_day8(EXAMPLE);
+        let source = disassemble(&instructions);
+        assert!(source.starts_with("    nop +0\nl1:\n    acc +1\n    jmp l6\n"));
+        assert_eq!(
+            assemble(&source, &InstructionSet::default()).unwrap(),
+            instructions
+        );
+
+        let instructions = parse_input_day8("jez b +2\njmp -5\nout b");
+        assert_eq!(
+            disassemble(&instructions),
+            "    jez b l2\n    jmp -5\nl2:\n    out b +0\n"
+        );
+    }
+
+    #[test]
+    fn assembly_errors() {
+        let set = InstructionSet::default();
+        let error = assemble("nop\nfoo +1", &set).unwrap_err();
+        assert_eq!(error.line, 2);
+        assert_eq!(error.kind, AssemblyErrorKind::UnknownOpCode("foo".into()));
+        assert_eq!(error.to_string(), "line 2: unknown opcode `foo`");
+
+        assert_eq!(
+            assemble("jmp nowhere", &set).unwrap_err().kind,
+            AssemblyErrorKind::UnknownLabel("nowhere".into())
+        );
+        assert_eq!(
+            assemble("start: nop\nstart: nop", &set).unwrap_err().kind,
+            AssemblyErrorKind::DuplicateLabel("start".into())
+        );
+        assert_eq!(
+            assemble("b: nop", &set).unwrap_err().kind,
+            AssemblyErrorKind::InvalidLabel("b".into())
+        );
+        assert_eq!(
+            assemble("acc b +1 +2", &set).unwrap_err().kind,
+            AssemblyErrorKind::TooManyOperands
+        );
+    }
+}
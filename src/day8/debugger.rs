@@ -0,0 +1,391 @@
+use super::{parse_register, TerminationReason, VM};
+use std::io::{self, BufRead, Write};
+use std::str::FromStr;
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum Comparison {
+    Equal,
+    NotEqual,
+    Less,
+    LessOrEqual,
+    Greater,
+    GreaterOrEqual,
+}
+
+impl FromStr for Comparison {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Ok(match s {
+            "==" => Comparison::Equal,
+            "!=" => Comparison::NotEqual,
+            "<" => Comparison::Less,
+            "<=" => Comparison::LessOrEqual,
+            ">" => Comparison::Greater,
+            ">=" => Comparison::GreaterOrEqual,
+            _ => return Err(()),
+        })
+    }
+}
+
+impl Comparison {
+    fn holds(&self, lhs: isize, rhs: isize) -> bool {
+        match self {
+            Comparison::Equal => lhs == rhs,
+            Comparison::NotEqual => lhs != rhs,
+            Comparison::Less => lhs < rhs,
+            Comparison::LessOrEqual => lhs <= rhs,
+            Comparison::Greater => lhs > rhs,
+            Comparison::GreaterOrEqual => lhs >= rhs,
+        }
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum Breakpoint {
+    /// stop before the instruction at this address gets executed
+    Address(usize),
+    /// stop as soon as the accumulator starts to satisfy the comparison
+    Accumulator(Comparison, isize),
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum StopReason {
+    /// a single instruction was executed
+    Step,
+    /// the breakpoint with this id was hit
+    Breakpoint(usize),
+    /// the watched accumulator was changed by the last instruction
+    Watchpoint { old: isize, new: isize },
+    /// the VM cannot execute any more instructions
+    Terminated(TerminationReason),
+}
+
+pub struct Debugger {
+    vm: VM,
+    /// breakpoints are identified by their index, deleted ones stay as `None`
+    breakpoints: Vec<Option<Breakpoint>>,
+    watch_accumulator: bool,
+    /// the address execution last stopped at, continuing from there does not stop again
+    stopped_at: Option<usize>,
+}
+
+impl Debugger {
+    pub fn new(vm: VM) -> Self {
+        Debugger {
+            vm,
+            breakpoints: Vec::new(),
+            watch_accumulator: false,
+            stopped_at: None,
+        }
+    }
+
+    pub fn vm(&self) -> &VM {
+        &self.vm
+    }
+
+    pub fn reset(&mut self) {
+        self.vm.reset();
+        self.stopped_at = None;
+    }
+
+    /// Adds a breakpoint and returns its id.
+    pub fn add_breakpoint(&mut self, breakpoint: Breakpoint) -> usize {
+        self.breakpoints.push(Some(breakpoint));
+        self.breakpoints.len() - 1
+    }
+
+    pub fn remove_breakpoint(&mut self, id: usize) -> bool {
+        self.breakpoints
+            .get_mut(id)
+            .and_then(|breakpoint| breakpoint.take())
+            .is_some()
+    }
+
+    pub fn breakpoints(&self) -> impl Iterator<Item = (usize, &Breakpoint)> {
+        self.breakpoints
+            .iter()
+            .enumerate()
+            .filter_map(|(id, breakpoint)| Some((id, breakpoint.as_ref()?)))
+    }
+
+    pub fn watch_accumulator(&mut self, enabled: bool) {
+        self.watch_accumulator = enabled;
+    }
+
+    /// the first breakpoint on the current address
+    fn address_breakpoint(&self) -> Option<usize> {
+        self.breakpoints()
+            .find_map(|(id, breakpoint)| match breakpoint {
+                Breakpoint::Address(address) if *address == self.vm.address => Some(id),
+                _ => None,
+            })
+    }
+
+    pub fn step(&mut self) -> StopReason {
+        let reason = self.execute();
+        self.stopped_at = Some(self.vm.address);
+        reason
+    }
+
+    fn execute(&mut self) -> StopReason {
+        let before = self.vm.accumulator();
+        if let Some(reason) = self.vm.step() {
+            return StopReason::Terminated(reason);
+        }
+        let after = self.vm.accumulator();
+
+        if self.watch_accumulator && before != after {
+            return StopReason::Watchpoint {
+                old: before,
+                new: after,
+            };
+        }
+        for (id, breakpoint) in self.breakpoints() {
+            if let Breakpoint::Accumulator(comparison, value) = breakpoint {
+                if comparison.holds(after, *value) && !comparison.holds(before, *value) {
+                    return StopReason::Breakpoint(id);
+                }
+            }
+        }
+        // checked after every instruction, so the next one is not executed yet
+        match self.address_breakpoint() {
+            Some(id) => StopReason::Breakpoint(id),
+            None => StopReason::Step,
+        }
+    }
+
+    /// Executes instructions until a breakpoint or watchpoint is hit or the VM terminates.
+    /// Stops right away at a breakpoint on the current address, unless execution already
+    /// stopped there, so continuing from a breakpoint does not hit it again.
+    pub fn continue_execution(&mut self) -> StopReason {
+        if self.stopped_at != Some(self.vm.address) {
+            if let Some(id) = self.address_breakpoint() {
+                self.stopped_at = Some(self.vm.address);
+                return StopReason::Breakpoint(id);
+            }
+        }
+        loop {
+            match self.step() {
+                StopReason::Step => continue,
+                reason => return reason,
+            }
+        }
+    }
+}
+
+const HELP: &str = "commands:
+  step [n]               execute n instructions (default 1)
+  continue               run until a breakpoint, watchpoint or termination
+  break <address>        stop before executing the instruction at address
+  break acc <op> <value> stop when the accumulator starts to satisfy op (==, !=, <, <=, >, >=)
+  delete <id>            remove a breakpoint
+  watch acc              stop whenever the accumulator changes
+  unwatch acc            stop watching the accumulator
+  print acc|<register>|addr|executed|output|breakpoints
+  list                   show the instructions around the current address
+  reset                  restart the program
+  quit";
+
+fn report(debugger: &Debugger, reason: StopReason, output: &mut impl Write) -> io::Result<()> {
+    match reason {
+        StopReason::Step => {}
+        StopReason::Breakpoint(id) => writeln!(output, "breakpoint {} hit", id)?,
+        StopReason::Watchpoint { old, new } => {
+            writeln!(output, "accumulator changed: {} -> {}", old, new)?
+        }
+        StopReason::Terminated(reason) => {
+            return writeln!(output, "program terminated: {:?}", reason);
+        }
+    }
+    let vm = debugger.vm();
+    match vm.instructions.get(vm.address) {
+        Some(instruction) => writeln!(output, "{}: {}", vm.address, instruction),
+        None => writeln!(output, "{}: <no instruction>", vm.address),
+    }
+}
+
+fn parse_breakpoint(args: &[&str]) -> Option<Breakpoint> {
+    Some(match args {
+        [address] => Breakpoint::Address(address.parse().ok()?),
+        ["acc", comparison, value] => {
+            Breakpoint::Accumulator(comparison.parse().ok()?, value.parse().ok()?)
+        }
+        _ => return None,
+    })
+}
+
+/// Runs an interactive debugging session, reading commands line by line until `quit` or end of input.
+pub fn repl<R: BufRead, W: Write>(
+    debugger: &mut Debugger,
+    input: R,
+    mut output: W,
+) -> io::Result<()> {
+    write!(output, "(day8) ")?;
+    output.flush()?;
+    for line in input.lines() {
+        let line = line?;
+        let words = line.split_whitespace().collect::<Vec<&str>>();
+        match words.as_slice() {
+            [] => {}
+            ["quit"] | ["q"] => break,
+            ["help"] | ["h"] => writeln!(output, "{}", HELP)?,
+            ["step"] | ["s"] => {
+                let reason = debugger.step();
+                report(debugger, reason, &mut output)?;
+            }
+            ["step", n] | ["s", n] => match n.parse::<usize>() {
+                Ok(n) => {
+                    let mut reason = StopReason::Step;
+                    for _ in 0..n {
+                        reason = debugger.step();
+                        if reason != StopReason::Step {
+                            break;
+                        }
+                    }
+                    report(debugger, reason, &mut output)?;
+                }
+                Err(_) => writeln!(output, "invalid step count: {}", n)?,
+            },
+            ["continue"] | ["c"] => {
+                let reason = debugger.continue_execution();
+                report(debugger, reason, &mut output)?;
+            }
+            ["break", args @ ..] | ["b", args @ ..] => match parse_breakpoint(args) {
+                Some(breakpoint) => {
+                    let id = debugger.add_breakpoint(breakpoint);
+                    writeln!(output, "breakpoint {}: {:?}", id, breakpoint)?;
+                }
+                None => writeln!(output, "usage: break <address> | break acc <op> <value>")?,
+            },
+            ["delete", id] | ["d", id] => match id.parse() {
+                Ok(id) if debugger.remove_breakpoint(id) => {
+                    writeln!(output, "deleted breakpoint {}", id)?
+                }
+                _ => writeln!(output, "no breakpoint {}", id)?,
+            },
+            ["watch", "acc"] => debugger.watch_accumulator(true),
+            ["unwatch", "acc"] => debugger.watch_accumulator(false),
+            ["print", what] | ["p", what] => match *what {
+                "acc" => writeln!(output, "{}", debugger.vm().accumulator())?,
+                "addr" => writeln!(output, "{}", debugger.vm().address)?,
+                "executed" => {
+                    let mut executed = debugger.vm().executed.iter().collect::<Vec<_>>();
+                    executed.sort();
+                    writeln!(output, "{:?}", executed)?;
+                }
+                "breakpoints" => {
+                    for (id, breakpoint) in debugger.breakpoints() {
+                        writeln!(output, "{}: {:?}", id, breakpoint)?;
+                    }
+                }
+                "output" => writeln!(output, "{:?}", debugger.vm().output())?,
+                register => match parse_register(register) {
+                    Some(register) => writeln!(output, "{}", debugger.vm().registers()[register])?,
+                    None => writeln!(output, "cannot print {}", what)?,
+                },
+            },
+            ["list"] | ["l"] => {
+                let vm = debugger.vm();
+                let start = vm.address.saturating_sub(5);
+                for (address, instruction) in
+                    vm.instructions.iter().enumerate().skip(start).take(11)
+                {
+                    let marker = if address == vm.address { "=>" } else { "  " };
+                    writeln!(output, "{} {}: {}", marker, address, instruction)?;
+                }
+            }
+            ["reset"] => {
+                debugger.reset();
+                report(debugger, StopReason::Step, &mut output)?;
+            }
+            _ => writeln!(output, "unknown command: {} (try help)", line.trim())?,
+        }
+        write!(output, "(day8) ")?;
+        output.flush()?;
+    }
+    writeln!(output)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::day8::{parse_input_day8, EXAMPLE};
+
+    fn debugger() -> Debugger {
+        Debugger::new(VM::from_instructions(&parse_input_day8(EXAMPLE)))
+    }
+
+    #[test]
+    fn single_step() {
+        let mut debugger = debugger();
+        assert_eq!(debugger.step(), StopReason::Step);
+        assert_eq!(debugger.step(), StopReason::Step);
+        assert_eq!(debugger.vm().address(), 2);
+        assert_eq!(debugger.vm().accumulator(), 1);
+        assert_eq!(debugger.vm().executed().len(), 2);
+    }
+
+    #[test]
+    fn address_breakpoint() {
+        let mut debugger = debugger();
+        let id = debugger.add_breakpoint(Breakpoint::Address(4));
+        assert_eq!(debugger.continue_execution(), StopReason::Breakpoint(id));
+        assert_eq!(debugger.vm().address(), 4);
+        assert_eq!(debugger.vm().accumulator(), 5);
+        assert_eq!(
+            debugger.continue_execution(),
+            StopReason::Terminated(TerminationReason::InfiniteLoop)
+        );
+        assert_eq!(debugger.vm().accumulator(), 5);
+    }
+
+    #[test]
+    fn breakpoint_on_start_address() {
+        let mut debugger = debugger();
+        let id = debugger.add_breakpoint(Breakpoint::Address(0));
+        assert_eq!(debugger.continue_execution(), StopReason::Breakpoint(id));
+        assert_eq!(debugger.vm().address(), 0);
+        assert!(debugger.vm().executed().is_empty());
+        assert_eq!(
+            debugger.continue_execution(),
+            StopReason::Terminated(TerminationReason::InfiniteLoop)
+        );
+
+        debugger.reset();
+        assert_eq!(debugger.continue_execution(), StopReason::Breakpoint(id));
+        assert!(debugger.vm().executed().is_empty());
+    }
+
+    #[test]
+    fn accumulator_breakpoint_and_watchpoint() {
+        let mut debugger = debugger();
+        let id = debugger.add_breakpoint(Breakpoint::Accumulator(Comparison::Greater, 1));
+        assert_eq!(debugger.continue_execution(), StopReason::Breakpoint(id));
+        assert_eq!(debugger.vm().accumulator(), 2);
+
+        assert!(debugger.remove_breakpoint(id));
+        debugger.watch_accumulator(true);
+        assert_eq!(
+            debugger.continue_execution(),
+            StopReason::Watchpoint { old: 2, new: 5 }
+        );
+    }
+
+    #[test]
+    fn repl_session() {
+        let mut debugger = debugger();
+        let mut output = Vec::new();
+        repl(
+            &mut debugger,
+            "break 4\ncontinue\nprint acc\nstep\nprint executed\nquit\n".as_bytes(),
+            &mut output,
+        )
+        .unwrap();
+        let output = String::from_utf8(output).unwrap();
+        assert!(output.contains("breakpoint 0 hit\n4: jmp -3\n"));
+        assert!(output.contains("(day8) 5\n"));
+        assert!(output.contains("1: acc +1\n"));
+        assert!(output.contains("[0, 1, 2, 3, 4, 6, 7]\n"));
+    }
+}
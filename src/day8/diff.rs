@@ -0,0 +1,126 @@
+use super::{Instruction, Registers, TerminationReason, VM};
+
+/// What one VM did in the step where the two runs diverged.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Observation {
+    /// address of the executed instruction
+    pub address: usize,
+    /// `None` if the VM terminated instead
+    pub instruction: Option<Instruction>,
+    pub termination: Option<TerminationReason>,
+    /// address of the next instruction
+    pub next_address: usize,
+    pub registers: Registers,
+    pub output: Option<isize>,
+}
+
+impl Observation {
+    fn step(vm: &mut VM) -> Self {
+        let address = vm.address();
+        let instruction = vm.instructions().get(address).copied();
+        let output_len = vm.output().len();
+        let termination = vm.step();
+        Observation {
+            address,
+            instruction: instruction.filter(|_| termination.is_none()),
+            termination,
+            next_address: vm.address(),
+            registers: *vm.registers(),
+            output: vm.output().get(output_len).copied(),
+        }
+    }
+
+    /// whether both VMs ended up in the same state, regardless of the executed instruction
+    fn same_outcome(&self, other: &Observation) -> bool {
+        self.termination == other.termination
+            && self.next_address == other.next_address
+            && self.registers == other.registers
+            && self.output == other.output
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Divergence {
+    /// number of steps both VMs executed with the same outcome before diverging
+    pub steps: usize,
+    pub left: Observation,
+    pub right: Observation,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum DiffResult {
+    /// both VMs went through the same states and terminated the same way
+    Identical {
+        steps: usize,
+        termination: TerminationReason,
+    },
+    Diverged(Box<Divergence>),
+}
+
+/// Runs two VMs (e.g. a program and a patched variant) in lockstep
+/// until their states differ or both terminate.
+pub fn diff_runs(left: &mut VM, right: &mut VM) -> DiffResult {
+    let mut steps = 0;
+    loop {
+        let left_step = Observation::step(left);
+        let right_step = Observation::step(right);
+        if !left_step.same_outcome(&right_step) {
+            return DiffResult::Diverged(Box::new(Divergence {
+                steps,
+                left: left_step,
+                right: right_step,
+            }));
+        }
+        if let Some(termination) = left_step.termination {
+            return DiffResult::Identical { steps, termination };
+        }
+        steps += 1;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::day8::{parse_input_day8, OpCode, EXAMPLE};
+
+    #[test]
+    fn first_divergence() {
+        let mut original = VM::from_instructions(&parse_input_day8(EXAMPLE));
+        let mut patched = original.clone();
+        patched.patch(
+            7,
+            Instruction {
+                opcode: OpCode::Nop,
+                register: 0,
+                operand: -4,
+            },
+        );
+
+        let divergence = match diff_runs(&mut original, &mut patched) {
+            DiffResult::Diverged(divergence) => divergence,
+            result => panic!("unexpected {:?}", result),
+        };
+        assert_eq!(divergence.steps, 4);
+        assert_eq!(divergence.left.address, 7);
+        assert_eq!(divergence.left.next_address, 3);
+        assert_eq!(divergence.right.address, 7);
+        assert_eq!(divergence.right.next_address, 8);
+        assert_eq!(divergence.right.instruction.unwrap().opcode, OpCode::Nop);
+    }
+
+    #[test]
+    fn identical_runs() {
+        let mut original = VM::from_instructions(&parse_input_day8(EXAMPLE));
+        // patching an unreachable instruction does not change anything
+        let mut patched = original.clone();
+        patched.patch(5, parse_input_day8("acc +99")[0]);
+
+        assert_eq!(
+            diff_runs(&mut original, &mut patched),
+            DiffResult::Identical {
+                steps: 7,
+                termination: TerminationReason::InfiniteLoop
+            }
+        );
+    }
+}
@@ -0,0 +1,188 @@
+use super::{parse_register, Instruction, OpCode, Registers};
+use lazy_static::lazy_static;
+use std::collections::{HashMap, HashSet};
+use std::sync::Mutex;
+
+/// What the VM does after a custom instruction was executed.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum Flow {
+    /// continue with the next instruction
+    Next,
+    /// jump relative to the current address
+    Jump(isize),
+    /// write a value to the output and continue with the next instruction
+    Output(isize),
+    /// stop the VM
+    Halt,
+}
+
+pub type OpHandler = fn(&mut Registers, &Instruction) -> Flow;
+
+lazy_static! {
+    /// names of all custom opcodes ever registered, shared by all instruction sets
+    static ref NAMES: Mutex<HashSet<&'static str>> = Mutex::new(HashSet::new());
+}
+
+/// Returns a `'static` copy of `name`, so opcodes stay `Copy`. Every distinct name
+/// is only allocated once.
+fn intern(name: &str) -> &'static str {
+    let mut names = NAMES.lock().unwrap();
+    match names.get(name) {
+        Some(name) => name,
+        None => {
+            let name: &'static str = Box::leak(name.into());
+            names.insert(name);
+            name
+        }
+    }
+}
+
+/// The built-in opcodes plus any number of custom ones.
+#[derive(Clone, Default)]
+pub struct InstructionSet {
+    custom: HashMap<&'static str, OpHandler>,
+}
+
+impl InstructionSet {
+    /// Registers a custom opcode, replacing an earlier one with the same name.
+    /// Built-in opcodes cannot be replaced, names that could not be parsed back
+    /// (empty or containing whitespace or comment characters) are rejected.
+    pub fn register(&mut self, name: &str, handler: OpHandler) -> Option<OpCode> {
+        if OpCode::builtin(name).is_some()
+            || name.is_empty()
+            || name.contains(|c: char| c.is_whitespace() || c == ';' || c == '#')
+        {
+            return None;
+        }
+        let name = intern(name);
+        self.custom.insert(name, handler);
+        Some(OpCode::Custom(name))
+    }
+
+    pub(super) fn handler(&self, name: &str) -> Option<OpHandler> {
+        self.custom.get(name).copied()
+    }
+
+    pub fn opcode(&self, name: &str) -> Option<OpCode> {
+        OpCode::builtin(name).or_else(|| {
+            let (name, _) = self.custom.get_key_value(name)?;
+            Some(OpCode::Custom(name))
+        })
+    }
+
+    /// Parses `<opcode> [register] [operand]`, the register defaults to the accumulator
+    /// and the operand to `+0`. Anything after `;` or `#` is a comment.
+    pub fn parse_instruction(&self, s: &str) -> Option<Instruction> {
+        let mut parts = strip_comment(s).split_whitespace();
+        let opcode = self.opcode(parts.next()?)?;
+
+        let mut register = 0;
+        let mut operand = 0;
+        let mut next = parts.next();
+        if let Some(r) = next.and_then(parse_register) {
+            register = r;
+            next = parts.next();
+        }
+        if let Some(o) = next {
+            operand = o.parse().ok()?;
+        }
+        if parts.next().is_some() {
+            return None;
+        }
+
+        Some(Instruction {
+            opcode,
+            register,
+            operand,
+        })
+    }
+
+    /// Parses one instruction per line, skipping empty and comment lines.
+    pub fn parse_program(&self, input: &str) -> Option<Vec<Instruction>> {
+        input
+            .lines()
+            .filter(|line| !strip_comment(line).trim().is_empty())
+            .map(|line| self.parse_instruction(line))
+            .collect()
+    }
+}
+
+pub(super) fn strip_comment(line: &str) -> &str {
+    match line.find([';', '#']) {
+        Some(start) => &line[..start],
+        None => line,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::day8::{TerminationReason, VM};
+
+    fn square(registers: &mut Registers, instruction: &Instruction) -> Flow {
+        let value = &mut registers[instruction.register];
+        *value *= *value;
+        Flow::Next
+    }
+
+    fn skip_if_even(registers: &mut Registers, instruction: &Instruction) -> Flow {
+        if registers[instruction.register] % 2 == 0 {
+            Flow::Jump(2)
+        } else {
+            Flow::Next
+        }
+    }
+
+    #[test]
+    fn parse_instructions() {
+        let set = InstructionSet::default();
+        let instruction = set.parse_instruction("mul c -3").unwrap();
+        assert_eq!(instruction.opcode, OpCode::Mul);
+        assert_eq!(instruction.register, 2);
+        assert_eq!(instruction.operand, -3);
+        assert_eq!(instruction.to_string(), "mul c -3");
+        assert_eq!(set.parse_instruction("out").unwrap().to_string(), "out +0");
+        assert!(set.parse_instruction("sqr a").is_none());
+        assert!(set.parse_instruction("acc +1 +2").is_none());
+        assert_eq!(
+            set.parse_instruction("  jmp   -4 ; back to the start")
+                .unwrap()
+                .to_string(),
+            "jmp -4"
+        );
+    }
+
+    #[test]
+    fn custom_opcodes() {
+        let mut set = InstructionSet::default();
+        assert_eq!(set.register("sqr", square), Some(OpCode::Custom("sqr")));
+        assert!(set.register("skpe", skip_if_even).is_some());
+        assert!(set.register("jmp", square).is_none());
+        assert!(set.register("", square).is_none());
+        assert!(set.register("sq r", square).is_none());
+
+        let program = set
+            .parse_program("acc b +3\nsqr b\nout b\nskpe b\nout +0\nhalt\nout +0")
+            .unwrap();
+        let mut vm = VM::with_instruction_set(&program, set);
+        assert_eq!(vm.run(), TerminationReason::Halted);
+        assert_eq!(vm.registers()[1], 9);
+        assert_eq!(vm.output(), &[9, 0]);
+    }
+
+    #[test]
+    fn opcode_names_from_runtime_data() {
+        let config = String::from("square = sqr2\n");
+        let name = config.trim_end().rsplit(' ').next().unwrap().to_string();
+        let mut set = InstructionSet::default();
+        let opcode = set.register(&name, square).unwrap();
+        drop((config, name));
+
+        let program = set.parse_program("acc +5\nsqr2").unwrap();
+        assert_eq!(program[1].opcode, opcode);
+        assert_eq!(program[1].to_string(), "sqr2 +0");
+        let mut vm = VM::with_instruction_set(&program, set);
+        assert_eq!(vm.run(), TerminationReason::EndOfInstructions);
+        assert_eq!(vm.accumulator(), 25);
+    }
+}
@@ -0,0 +1,268 @@
+use super::{register_name, Instruction, OpCode, VM};
+use core::fmt;
+use std::collections::HashMap;
+use std::fmt::{Display, Formatter};
+use std::io::{self, Write};
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct TraceEntry {
+    pub address: usize,
+    pub opcode: OpCode,
+    /// the register the instruction works on, `before` and `after` are its values
+    pub register: usize,
+    pub operand: isize,
+    pub before: isize,
+    pub after: isize,
+}
+
+impl TraceEntry {
+    pub fn instruction(&self) -> Instruction {
+        Instruction {
+            opcode: self.opcode,
+            register: self.register,
+            operand: self.operand,
+        }
+    }
+}
+
+impl Display for TraceEntry {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{:>5}: {}\t{} {} -> {}",
+            self.address,
+            self.instruction(),
+            register_name(self.register),
+            self.before,
+            self.after
+        )
+    }
+}
+
+/// All instructions executed by a [`VM`] in order of execution.
+#[derive(Clone, Debug, Default)]
+pub struct Trace {
+    entries: Vec<TraceEntry>,
+}
+
+impl Trace {
+    pub(super) fn record(&mut self, entry: TraceEntry) {
+        self.entries.push(entry);
+    }
+
+    pub(super) fn clear(&mut self) {
+        self.entries.clear();
+    }
+
+    pub fn entries(&self) -> &[TraceEntry] {
+        &self.entries
+    }
+
+    /// Returns the instructions executed since `reentry_address` was last entered,
+    /// i.e. the body of the loop if the VM is about to execute `reentry_address` again.
+    pub fn loop_body(&self, reentry_address: usize) -> Option<&[TraceEntry]> {
+        let start = self
+            .entries
+            .iter()
+            .rposition(|entry| entry.address == reentry_address)?;
+        Some(&self.entries[start..])
+    }
+
+    pub fn summary(&self, hot_addresses: usize) -> TraceSummary {
+        let mut opcode_counts = HashMap::new();
+        let mut address_counts = HashMap::new();
+        for entry in &self.entries {
+            *opcode_counts.entry(entry.opcode).or_insert(0) += 1;
+            *address_counts.entry(entry.address).or_insert(0) += 1;
+        }
+
+        let mut hot = address_counts.into_iter().collect::<Vec<(usize, usize)>>();
+        // most executed first, lower addresses first on ties
+        hot.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(&b.0)));
+        hot.truncate(hot_addresses);
+
+        TraceSummary {
+            executed: self.entries.len(),
+            opcode_counts,
+            hot_addresses: hot,
+        }
+    }
+
+    pub fn write_text<W: Write>(&self, mut output: W) -> io::Result<()> {
+        for entry in &self.entries {
+            writeln!(output, "{}", entry)?;
+        }
+        Ok(())
+    }
+
+    /// Writes one JSON object per executed instruction.
+    pub fn write_json_lines<W: Write>(&self, mut output: W) -> io::Result<()> {
+        for entry in &self.entries {
+            writeln!(
+                output,
+                r#"{{"address":{},"opcode":{},"register":"{}","operand":{},"before":{},"after":{}}}"#,
+                entry.address,
+                json_string(&entry.opcode.to_string()),
+                register_name(entry.register),
+                entry.operand,
+                entry.before,
+                entry.after
+            )?;
+        }
+        Ok(())
+    }
+}
+
+/// `s` as a quoted JSON string, custom opcode names may contain any character.
+fn json_string(s: &str) -> String {
+    let mut json = String::with_capacity(s.len() + 2);
+    json.push('"');
+    for c in s.chars() {
+        match c {
+            '"' => json.push_str("\\\""),
+            '\\' => json.push_str("\\\\"),
+            '\n' => json.push_str("\\n"),
+            '\t' => json.push_str("\\t"),
+            c if c.is_control() => json.push_str(&format!("\\u{:04x}", c as u32)),
+            c => json.push(c),
+        }
+    }
+    json.push('"');
+    json
+}
+
+pub struct TraceSummary {
+    pub executed: usize,
+    pub opcode_counts: HashMap<OpCode, usize>,
+    /// `(address, count)` pairs, most executed first
+    pub hot_addresses: Vec<(usize, usize)>,
+}
+
+impl Display for TraceSummary {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        writeln!(f, "executed instructions: {}", self.executed)?;
+        let mut opcode_counts = self
+            .opcode_counts
+            .iter()
+            .map(|(opcode, count)| (opcode.to_string(), count))
+            .collect::<Vec<_>>();
+        opcode_counts.sort();
+        for (opcode, count) in opcode_counts {
+            writeln!(f, "  {}: {}", opcode, count)?;
+        }
+        writeln!(f, "hot addresses:")?;
+        for (address, count) in &self.hot_addresses {
+            writeln!(f, "  {:>5}: {}", address, count)?;
+        }
+        Ok(())
+    }
+}
+
+impl VM {
+    /// Returns the traced loop body after [`VM::run`] detected an infinite loop.
+    pub fn loop_body(&self) -> Option<&[TraceEntry]> {
+        self.trace.as_ref()?.loop_body(self.address)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::day8::instruction_set::{Flow, InstructionSet};
+    use crate::day8::{parse_input_day8, TerminationReason, EXAMPLE};
+
+    fn traced_run() -> VM {
+        let mut vm = VM::from_instructions(&parse_input_day8(EXAMPLE));
+        vm.enable_tracing();
+        assert_eq!(vm.run(), TerminationReason::InfiniteLoop);
+        vm
+    }
+
+    #[test]
+    fn loop_body() {
+        let vm = traced_run();
+        let body = vm
+            .loop_body()
+            .unwrap()
+            .iter()
+            .map(|entry| entry.address)
+            .collect::<Vec<usize>>();
+        assert_eq!(body, vec![1, 2, 6, 7, 3, 4]);
+    }
+
+    #[test]
+    fn summary() {
+        let summary = traced_run().trace().unwrap().summary(3);
+        assert_eq!(summary.executed, 7);
+        assert_eq!(summary.opcode_counts[&OpCode::Nop], 1);
+        assert_eq!(summary.opcode_counts[&OpCode::Acc], 3);
+        assert_eq!(summary.opcode_counts[&OpCode::Jmp], 3);
+        assert_eq!(summary.hot_addresses, vec![(0, 1), (1, 1), (2, 1)]);
+    }
+
+    #[test]
+    fn export() {
+        let vm = traced_run();
+        let trace = vm.trace().unwrap();
+
+        let mut text = Vec::new();
+        trace.write_text(&mut text).unwrap();
+        let text = String::from_utf8(text).unwrap();
+        assert_eq!(text.lines().nth(1), Some("    1: acc +1\ta 0 -> 1"));
+
+        let mut json = Vec::new();
+        trace.write_json_lines(&mut json).unwrap();
+        let json = String::from_utf8(json).unwrap();
+        assert_eq!(json.lines().count(), 7);
+        assert_eq!(
+            json.lines().last(),
+            Some(
+                r#"{"address":4,"opcode":"jmp","register":"a","operand":-3,"before":5,"after":5}"#
+            )
+        );
+    }
+
+    #[test]
+    fn json_escapes_opcode_names() {
+        fn skip(_: &mut crate::day8::Registers, _: &Instruction) -> Flow {
+            Flow::Next
+        }
+
+        let mut set = InstructionSet::default();
+        set.register(r#"say"\hi"#, skip).unwrap();
+        let program = set.parse_program(r#"say"\hi"#).unwrap();
+        let mut vm = VM::with_instruction_set(&program, set);
+        vm.enable_tracing();
+        vm.run();
+
+        let mut json = Vec::new();
+        vm.trace().unwrap().write_json_lines(&mut json).unwrap();
+        assert_eq!(
+            String::from_utf8(json).unwrap().trim_end(),
+            r#"{"address":0,"opcode":"say\"\\hi","register":"a","operand":0,"before":0,"after":0}"#
+        );
+        assert_eq!(json_string("\u{1}\n"), r#""\u0001\n""#);
+    }
+
+    #[test]
+    fn named_registers() {
+        let mut vm = VM::from_instructions(&parse_input_day8("acc b +2\nmul b -3\nacc +1"));
+        vm.enable_tracing();
+        assert_eq!(vm.run(), TerminationReason::EndOfInstructions);
+        let lines = vm
+            .trace()
+            .unwrap()
+            .entries()
+            .iter()
+            .map(|entry| entry.to_string())
+            .collect::<Vec<String>>();
+        assert_eq!(
+            lines,
+            vec![
+                "    0: acc b +2\tb 0 -> 2",
+                "    1: mul b -3\tb 2 -> -6",
+                "    2: acc +1\ta 0 -> 1"
+            ]
+        );
+    }
+}
@@ -1,5 +1,9 @@
 use aoc_runner_derive::{aoc, aoc_generator};
-use std::collections::HashSet;
+use std::collections::{HashMap, VecDeque};
+use std::io::{self, BufRead, Lines};
+
+/// preamble size of the puzzle input
+pub const DEFAULT_PREAMBLE_SIZE: usize = 25;
 
 #[aoc_generator(day9)]
 fn parse_input_day9(input: &str) -> Vec<usize> {
@@ -9,27 +13,60 @@ fn parse_input_day9(input: &str) -> Vec<usize> {
         .collect::<Vec<usize>>()
 }
 
+/// Counts how often each number occurs, so evicting one copy of a duplicate keeps the others.
+#[derive(Default)]
+struct Multiset {
+    counts: HashMap<usize, usize>,
+}
+
+impl Multiset {
+    fn with_capacity(capacity: usize) -> Self {
+        Multiset {
+            counts: HashMap::with_capacity(capacity),
+        }
+    }
+
+    fn insert(&mut self, n: usize) {
+        *self.counts.entry(n).or_insert(0) += 1;
+    }
+
+    fn remove(&mut self, n: usize) {
+        if let Some(count) = self.counts.get_mut(&n) {
+            *count -= 1;
+            if *count == 0 {
+                self.counts.remove(&n);
+            }
+        }
+    }
+
+    fn contains(&self, n: usize) -> bool {
+        self.counts.contains_key(&n)
+    }
+}
+
 struct CipherState {
     size: usize,
-    window: Vec<usize>,
-    lookup: HashSet<usize>,
+    /// ring buffer of the last `size` numbers, oldest first
+    window: VecDeque<usize>,
+    lookup: Multiset,
 }
 
 impl CipherState {
     fn with_size(size: usize) -> Self {
         CipherState {
             size,
-            window: Vec::with_capacity(size),
-            lookup: HashSet::with_capacity(size),
+            window: VecDeque::with_capacity(size),
+            lookup: Multiset::with_capacity(size),
         }
     }
 
     fn push(&mut self, n: usize) {
         if self.window.len() == self.size {
-            let evicted = self.window.remove(0);
-            self.lookup.remove(&evicted);
+            if let Some(evicted) = self.window.pop_front() {
+                self.lookup.remove(evicted);
+            }
         }
-        self.window.push(n);
+        self.window.push_back(n);
         self.lookup.insert(n);
     }
 
@@ -42,7 +79,7 @@ impl CipherState {
                 continue;
             }
             let remaining = n - candidate;
-            if remaining != *candidate && self.lookup.contains(&remaining) {
+            if remaining != *candidate && self.lookup.contains(remaining) {
                 return true;
             }
         }
@@ -50,7 +87,7 @@ impl CipherState {
     }
 }
 
-fn find_first_bad_num(input: &Vec<usize>, preamble_size: usize) -> Option<usize> {
+fn find_first_bad_num(input: &[usize], preamble_size: usize) -> Option<usize> {
     let mut state = CipherState::with_size(preamble_size);
     for n in input {
         if state.check(n) {
@@ -63,24 +100,86 @@ fn find_first_bad_num(input: &Vec<usize>, preamble_size: usize) -> Option<usize>
 }
 
 #[aoc(day9, part1)]
-fn part1(input: &Vec<usize>) -> Option<usize> {
-    find_first_bad_num(input, 25)
+fn part1(input: &[usize]) -> Option<usize> {
+    find_first_bad_num(input, DEFAULT_PREAMBLE_SIZE)
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Violation {
+    /// position in the stream, starting at 0
+    pub index: usize,
+    pub value: usize,
+}
+
+/// Iterator over all numbers of a stream that are not the sum of two
+/// different numbers among the previous ones, see [`validate_stream`].
+pub struct Violations<R> {
+    lines: Lines<R>,
+    state: CipherState,
+    index: usize,
+}
+
+impl<R: BufRead> Iterator for Violations<R> {
+    type Item = io::Result<Violation>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        for line in &mut self.lines {
+            let line = match line {
+                Ok(line) => line,
+                Err(e) => return Some(Err(e)),
+            };
+            let line = line.trim();
+            if line.is_empty() {
+                continue;
+            }
+            let value = match line.parse::<usize>() {
+                Ok(value) => value,
+                Err(e) => return Some(Err(io::Error::new(io::ErrorKind::InvalidData, e))),
+            };
+
+            let index = self.index;
+            self.index += 1;
+            // invalid numbers still become part of the window for the following ones
+            let valid = self.state.check(&value);
+            self.state.push(value);
+            if !valid {
+                return Some(Ok(Violation { index, value }));
+            }
+        }
+        None
+    }
+}
+
+/// Validates one number per line without loading the whole stream into memory.
+///
+/// Fails for a preamble of 0, the window could never evict old numbers then.
+pub fn validate_stream<R: BufRead>(reader: R, preamble_size: usize) -> io::Result<Violations<R>> {
+    if preamble_size == 0 {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            "the preamble needs at least one number",
+        ));
+    }
+    Ok(Violations {
+        lines: reader.lines(),
+        state: CipherState::with_size(preamble_size),
+        index: 0,
+    })
 }
 
 fn find_first_bad_num_sliced(input: &Vec<usize>, preamble_size: usize) -> Option<usize> {
-    let mut lookup = HashSet::with_capacity(preamble_size);
+    let mut lookup = Multiset::with_capacity(preamble_size);
     for (i, n) in input.iter().enumerate() {
         if i >= preamble_size {
             let window = &input[(i - preamble_size)..i];
-            if window
+            if !window
                 .iter()
-                .find(|m| **m <= *n && lookup.contains(&(*n - **m)))
-                .is_none()
+                .any(|m| *m <= *n && *n - *m != *m && lookup.contains(*n - *m))
             {
                 return Some(*n);
             }
 
-            lookup.remove(&input[i - preamble_size]);
+            lookup.remove(input[i - preamble_size]);
         }
         lookup.insert(*n);
     }
@@ -89,10 +188,10 @@ fn find_first_bad_num_sliced(input: &Vec<usize>, preamble_size: usize) -> Option
 
 #[aoc(day9, part1, sliced)]
 fn part1_sliced(input: &Vec<usize>) -> Option<usize> {
-    find_first_bad_num_sliced(input, 25)
+    find_first_bad_num_sliced(input, DEFAULT_PREAMBLE_SIZE)
 }
 
-fn find_weakness(input: &Vec<usize>, preamble_size: usize) -> Option<usize> {
+fn find_weakness(input: &[usize], preamble_size: usize) -> Option<usize> {
     let bad_num = find_first_bad_num(input, preamble_size)?;
     for (i, n) in input.iter().enumerate() {
         if *n == bad_num {
@@ -118,8 +217,8 @@ fn find_weakness(input: &Vec<usize>, preamble_size: usize) -> Option<usize> {
 }
 
 #[aoc(day9, part2)]
-fn part2(input: &Vec<usize>) -> Option<usize> {
-    find_weakness(input, 25)
+fn part2(input: &[usize]) -> Option<usize> {
+    find_weakness(input, DEFAULT_PREAMBLE_SIZE)
 }
 
 fn find_weakness_sliding_window(input: &Vec<usize>, preamble_size: usize) -> Option<usize> {
@@ -145,12 +244,79 @@ fn find_weakness_sliding_window(input: &Vec<usize>, preamble_size: usize) -> Opt
 
 #[aoc(day9, part2, sliding_window)]
 fn part2_sliding_window(input: &Vec<usize>) -> Option<usize> {
-    find_weakness_sliding_window(input, 25)
+    find_weakness_sliding_window(input, DEFAULT_PREAMBLE_SIZE)
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct ContiguousRange {
+    pub start: usize,
+    /// inclusive
+    pub end: usize,
+    pub min: i64,
+    pub max: i64,
+}
+
+impl ContiguousRange {
+    pub fn weakness(&self) -> i64 {
+        self.min + self.max
+    }
+}
+
+/// Finds all ranges of at least `min_len` contiguous values that sum to `target`,
+/// ordered by start and end index.
+///
+/// A range `i..=j` sums to `target` exactly if `prefix[j + 1] - prefix[i] == target`,
+/// so remembering where each prefix sum occurred finds all ranges in one pass,
+/// which unlike a sliding window also works for negative values.
+pub fn find_contiguous_ranges(values: &[i64], target: i64, min_len: usize) -> Vec<ContiguousRange> {
+    let mut prefix_positions: HashMap<i64, Vec<usize>> = HashMap::new();
+    prefix_positions.insert(0, vec![0]);
+
+    let mut ranges = Vec::new();
+    let mut prefix = 0;
+    for (end, value) in values.iter().enumerate() {
+        prefix += value;
+        if let Some(starts) = prefix_positions.get(&(prefix - target)) {
+            for start in starts
+                .iter()
+                .filter(|start| end + 1 - **start >= min_len.max(1))
+            {
+                let range = &values[*start..=end];
+                ranges.push(ContiguousRange {
+                    start: *start,
+                    end,
+                    min: *range.iter().min().unwrap(),
+                    max: *range.iter().max().unwrap(),
+                });
+            }
+        }
+        prefix_positions.entry(prefix).or_default().push(end + 1);
+    }
+
+    ranges.sort_by_key(|range| (range.start, range.end));
+    ranges
+}
+
+fn find_weakness_prefix_sums(input: &[usize], preamble_size: usize) -> Option<usize> {
+    let bad_num = find_first_bad_num(input, preamble_size)?;
+    let bad_num_index = input.iter().position(|n| *n == bad_num)?;
+
+    let values = input.iter().map(|n| *n as i64).collect::<Vec<i64>>();
+    find_contiguous_ranges(&values, bad_num as i64, 2)
+        .into_iter()
+        .find(|range| range.end < bad_num_index)
+        .map(|range| range.weakness() as usize)
+}
+
+#[aoc(day9, part2, prefix_sums)]
+fn part2_prefix_sums(input: &[usize]) -> Option<usize> {
+    find_weakness_prefix_sums(input, DEFAULT_PREAMBLE_SIZE)
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use rand::Rng;
 
     const INPUT: &str = "35
 20
@@ -186,6 +352,115 @@ mod tests {
         );
     }
 
+    #[test]
+    fn stream_reports_all_violations() {
+        let violations = validate_stream(INPUT.as_bytes(), 5)
+            .unwrap()
+            .collect::<io::Result<Vec<Violation>>>()
+            .unwrap();
+        assert_eq!(
+            violations[0],
+            Violation {
+                index: 14,
+                value: 127
+            }
+        );
+        assert_eq!(violations.len(), 1);
+
+        let violations = validate_stream("1\n2\n3\n10\n13\n100\n".as_bytes(), 2)
+            .unwrap()
+            .map(|v| v.unwrap().index)
+            .collect::<Vec<usize>>();
+        assert_eq!(violations, vec![3, 5]);
+    }
+
+    #[test]
+    fn stream_rejects_garbage() {
+        let mut violations = validate_stream("1\n2\nthree\n".as_bytes(), 1).unwrap();
+        assert!(violations.next().unwrap().is_ok());
+        assert_eq!(
+            violations.next().unwrap().unwrap_err().kind(),
+            io::ErrorKind::InvalidData
+        );
+    }
+
+    #[test]
+    fn stream_rejects_empty_preamble() {
+        let input = (1..20).map(|n| format!("{}\n", n)).collect::<String>();
+        assert_eq!(
+            validate_stream(input.as_bytes(), 0).err().map(|e| e.kind()),
+            Some(io::ErrorKind::InvalidInput)
+        );
+    }
+
+    #[test]
+    fn part2_prefix_sums_example() {
+        assert_eq!(
+            find_weakness_prefix_sums(&parse_input_day9(INPUT), 5),
+            Some(62)
+        );
+    }
+
+    #[test]
+    fn contiguous_ranges_with_negative_values() {
+        let ranges = find_contiguous_ranges(&[3, -1, 2, -2, 4], 2, 1)
+            .iter()
+            .map(|range| (range.start, range.end, range.weakness()))
+            .collect::<Vec<(usize, usize, i64)>>();
+        assert_eq!(ranges, vec![(0, 1, 2), (0, 3, 1), (2, 2, 4), (3, 4, 2)]);
+
+        assert_eq!(find_contiguous_ranges(&[3, -1, 2, -2, 4], 2, 3).len(), 1);
+    }
+
+    #[test]
+    fn prefix_sums_agree_with_brute_force() {
+        let mut rng = rand::thread_rng();
+        for _ in 0..200 {
+            let preamble_size = rng.gen_range(2, 5);
+            let input = (0..30)
+                .map(|_| rng.gen_range(0, 20))
+                .collect::<Vec<usize>>();
+            assert_eq!(
+                find_weakness(&input, preamble_size),
+                find_weakness_prefix_sums(&input, preamble_size),
+                "input {:?} with preamble {}",
+                input,
+                preamble_size
+            );
+        }
+    }
+
+    #[test]
+    fn duplicates_in_window() {
+        // evicting the first 1 and 2 must keep the second copies, so 3 = 1 + 2 stays valid
+        let input = vec![1, 2, 1, 2, 3, 3, 3];
+        assert_eq!(find_first_bad_num(&input, 4), None);
+        assert_eq!(find_first_bad_num_sliced(&input, 4), None);
+
+        // a number is not the sum of a value with itself, even if it occurs twice
+        let input = vec![2, 2, 7, 4];
+        assert_eq!(find_first_bad_num(&input, 3), Some(4));
+        assert_eq!(find_first_bad_num_sliced(&input, 3), Some(4));
+    }
+
+    #[test]
+    fn implementations_agree() {
+        let mut rng = rand::thread_rng();
+        for _ in 0..200 {
+            let preamble_size = rng.gen_range(2, 6);
+            let input = (0..40)
+                .map(|_| rng.gen_range(0, 12))
+                .collect::<Vec<usize>>();
+            assert_eq!(
+                find_first_bad_num(&input, preamble_size),
+                find_first_bad_num_sliced(&input, preamble_size),
+                "input {:?} with preamble {}",
+                input,
+                preamble_size
+            );
+        }
+    }
+
     #[test]
     fn part2_example() {
         assert_eq!(find_weakness(&mut parse_input_day9(INPUT), 5), Some(62));
@@ -0,0 +1,419 @@
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::fmt::{Display, Write};
+use std::hash::Hash;
+
+/// Returned where the graph has to be acyclic but is not, holds a node on a cycle.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Cycle<K>(pub K);
+
+struct Edge<W> {
+    from: usize,
+    to: usize,
+    weight: W,
+}
+
+/// Directed graph over arbitrary node keys with a weight on every edge.
+pub struct Graph<K, W = ()> {
+    keys: Vec<K>,
+    indices: HashMap<K, usize>,
+    edges: Vec<Edge<W>>,
+    /// edge indices per node
+    outgoing: Vec<Vec<usize>>,
+    incoming: Vec<Vec<usize>>,
+}
+
+impl<K: Clone + Eq + Hash, W> Default for Graph<K, W> {
+    fn default() -> Self {
+        Graph::new()
+    }
+}
+
+impl<K: Clone + Eq + Hash, W> Graph<K, W> {
+    pub fn new() -> Self {
+        Graph {
+            keys: Vec::new(),
+            indices: HashMap::new(),
+            edges: Vec::new(),
+            outgoing: Vec::new(),
+            incoming: Vec::new(),
+        }
+    }
+
+    fn index(&mut self, key: K) -> usize {
+        if let Some(index) = self.indices.get(&key) {
+            return *index;
+        }
+        let index = self.keys.len();
+        self.keys.push(key.clone());
+        self.indices.insert(key, index);
+        self.outgoing.push(Vec::new());
+        self.incoming.push(Vec::new());
+        index
+    }
+
+    /// Adds a node without any edges, does nothing if it already exists.
+    pub fn add_node(&mut self, key: K) {
+        self.index(key);
+    }
+
+    /// Adds an edge, and its nodes if they do not exist yet.
+    pub fn add_edge(&mut self, from: K, to: K, weight: W) {
+        let from = self.index(from);
+        let to = self.index(to);
+        self.outgoing[from].push(self.edges.len());
+        self.incoming[to].push(self.edges.len());
+        self.edges.push(Edge { from, to, weight });
+    }
+
+    pub fn contains_node(&self, key: &K) -> bool {
+        self.indices.contains_key(key)
+    }
+
+    /// all nodes in insertion order
+    pub fn nodes(&self) -> impl Iterator<Item = &K> {
+        self.keys.iter()
+    }
+
+    pub fn node_count(&self) -> usize {
+        self.keys.len()
+    }
+
+    pub fn edge_count(&self) -> usize {
+        self.edges.len()
+    }
+
+    fn edges_of<'a>(
+        &'a self,
+        key: &K,
+        adjacency: &'a [Vec<usize>],
+    ) -> impl Iterator<Item = &'a Edge<W>> + 'a {
+        self.indices
+            .get(key)
+            .map(|index| &adjacency[*index])
+            .into_iter()
+            .flatten()
+            .map(move |edge| &self.edges[*edge])
+    }
+
+    /// targets and weights of the edges leaving `key`, in insertion order
+    pub fn successors<'a>(&'a self, key: &K) -> impl Iterator<Item = (&'a K, &'a W)> + 'a {
+        self.edges_of(key, &self.outgoing)
+            .map(move |edge| (&self.keys[edge.to], &edge.weight))
+    }
+
+    /// sources and weights of the edges entering `key`, in insertion order
+    pub fn predecessors<'a>(&'a self, key: &K) -> impl Iterator<Item = (&'a K, &'a W)> + 'a {
+        self.edges_of(key, &self.incoming)
+            .map(move |edge| (&self.keys[edge.from], &edge.weight))
+    }
+
+    fn traverse(&self, start: &K, adjacency: &[Vec<usize>], forward: bool) -> HashSet<&K> {
+        let mut visited = vec![false; self.keys.len()];
+        let mut stack = self
+            .indices
+            .get(start)
+            .into_iter()
+            .copied()
+            .collect::<Vec<usize>>();
+        while let Some(node) = stack.pop() {
+            for edge in &adjacency[node] {
+                let edge = &self.edges[*edge];
+                let next = if forward { edge.to } else { edge.from };
+                if !visited[next] {
+                    visited[next] = true;
+                    stack.push(next);
+                }
+            }
+        }
+        self.keys
+            .iter()
+            .zip(visited)
+            .filter_map(|(key, visited)| if visited { Some(key) } else { None })
+            .collect()
+    }
+
+    /// Nodes reachable from `start` over at least one edge, so `start` itself
+    /// is only included if it is part of a cycle.
+    pub fn reachable_from(&self, start: &K) -> HashSet<&K> {
+        self.traverse(start, &self.outgoing, true)
+    }
+
+    /// Nodes from which `target` is reachable over at least one edge.
+    pub fn reaching(&self, target: &K) -> HashSet<&K> {
+        self.traverse(target, &self.incoming, false)
+    }
+
+    fn topological_indices(&self) -> Result<Vec<usize>, Cycle<K>> {
+        let mut in_degree = self
+            .incoming
+            .iter()
+            .map(|edges| edges.len())
+            .collect::<Vec<usize>>();
+        let mut queue = (0..self.keys.len())
+            .filter(|node| in_degree[*node] == 0)
+            .collect::<VecDeque<usize>>();
+
+        let mut order = Vec::with_capacity(self.keys.len());
+        while let Some(node) = queue.pop_front() {
+            order.push(node);
+            for edge in &self.outgoing[node] {
+                let next = self.edges[*edge].to;
+                in_degree[next] -= 1;
+                if in_degree[next] == 0 {
+                    queue.push_back(next);
+                }
+            }
+        }
+        if order.len() == self.keys.len() {
+            return Ok(order);
+        }
+
+        // every remaining node has a remaining predecessor, so walking backwards
+        // has to end up on a cycle eventually
+        let mut node = (0..self.keys.len())
+            .find(|node| in_degree[*node] > 0)
+            .unwrap();
+        let mut visited = vec![false; self.keys.len()];
+        while !visited[node] {
+            visited[node] = true;
+            node = self.incoming[node]
+                .iter()
+                .map(|edge| self.edges[*edge].from)
+                .find(|from| in_degree[*from] > 0)
+                .unwrap();
+        }
+        Err(Cycle(self.keys[node].clone()))
+    }
+
+    /// Orders the nodes so that every edge points forward, fails if there is a cycle.
+    pub fn topological_sort(&self) -> Result<Vec<&K>, Cycle<K>> {
+        Ok(self
+            .topological_indices()?
+            .into_iter()
+            .map(|node| &self.keys[node])
+            .collect())
+    }
+
+    /// Computes a value for every node from the values of its successors
+    /// (with the weights of the edges leading there), starting at the sinks.
+    pub fn fold<T>(
+        &self,
+        mut f: impl FnMut(&K, &[(&K, &W, &T)]) -> T,
+    ) -> Result<HashMap<&K, T>, Cycle<K>> {
+        let order = self.topological_indices()?;
+        let mut values: Vec<Option<T>> = (0..self.keys.len()).map(|_| None).collect();
+        for node in order.into_iter().rev() {
+            let value = {
+                let successors = self.outgoing[node]
+                    .iter()
+                    .map(|edge| {
+                        let edge = &self.edges[*edge];
+                        let value = values[edge.to].as_ref().unwrap();
+                        (&self.keys[edge.to], &edge.weight, value)
+                    })
+                    .collect::<Vec<(&K, &W, &T)>>();
+                f(&self.keys[node], &successors)
+            };
+            values[node] = Some(value);
+        }
+        Ok(self
+            .keys
+            .iter()
+            .zip(values)
+            .map(|(key, value)| (key, value.unwrap()))
+            .collect())
+    }
+
+    /// Like [`Graph::fold`], but only evaluates the nodes reachable from `start` and returns
+    /// the value of `start`, `None` if it is not in the graph. Cycles elsewhere do not matter.
+    pub fn fold_from<T>(
+        &self,
+        start: &K,
+        mut f: impl FnMut(&K, &[(&K, &W, &T)]) -> T,
+    ) -> Result<Option<T>, Cycle<K>> {
+        let start = match self.indices.get(start) {
+            Some(start) => *start,
+            None => return Ok(None),
+        };
+        let mut values: Vec<Option<T>> = (0..self.keys.len()).map(|_| None).collect();
+        let mut on_stack = vec![false; self.keys.len()];
+        // depth-first, with the position of the next outgoing edge to follow
+        let mut stack = vec![(start, 0)];
+        on_stack[start] = true;
+        while let Some((node, next)) = stack.last_mut() {
+            let node = *node;
+            if let Some(edge) = self.outgoing[node].get(*next) {
+                *next += 1;
+                let to = self.edges[*edge].to;
+                if on_stack[to] {
+                    return Err(Cycle(self.keys[to].clone()));
+                }
+                if values[to].is_none() {
+                    on_stack[to] = true;
+                    stack.push((to, 0));
+                }
+                continue;
+            }
+            stack.pop();
+            on_stack[node] = false;
+            let value = {
+                let successors = self.outgoing[node]
+                    .iter()
+                    .map(|edge| {
+                        let edge = &self.edges[*edge];
+                        let value = values[edge.to].as_ref().unwrap();
+                        (&self.keys[edge.to], &edge.weight, value)
+                    })
+                    .collect::<Vec<(&K, &W, &T)>>();
+                f(&self.keys[node], &successors)
+            };
+            values[node] = Some(value);
+        }
+        Ok(values[start].take())
+    }
+
+    /// Counts the distinct paths from `from` to `to`, `None` if the count overflows.
+    pub fn count_paths(&self, from: &K, to: &K) -> Result<Option<u128>, Cycle<K>> {
+        let counts = self.fold(|node, successors| {
+            if node == to {
+                return Some(1u128);
+            }
+            successors
+                .iter()
+                .try_fold(0u128, |sum, (_, _, count)| sum.checked_add((**count)?))
+        })?;
+        Ok(counts.get(from).copied().unwrap_or(Some(0)))
+    }
+
+    /// Exports the graph in the Graphviz DOT format, labelling the edges
+    /// for which `edge_label` returns a label.
+    pub fn to_dot(&self, edge_label: impl Fn(&W) -> Option<String>) -> String
+    where
+        K: Display,
+    {
+        fn quote(s: &str) -> String {
+            format!("\"{}\"", s.replace('\\', "\\\\").replace('"', "\\\""))
+        }
+
+        let mut dot = String::from("digraph {\n");
+        for key in &self.keys {
+            writeln!(dot, "    {};", quote(&key.to_string())).unwrap();
+        }
+        for edge in &self.edges {
+            write!(
+                dot,
+                "    {} -> {}",
+                quote(&self.keys[edge.from].to_string()),
+                quote(&self.keys[edge.to].to_string())
+            )
+            .unwrap();
+            if let Some(label) = edge_label(&edge.weight) {
+                write!(dot, " [label={}]", quote(&label)).unwrap();
+            }
+            dot.push_str(";\n");
+        }
+        dot.push_str("}\n");
+        dot
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn diamond() -> Graph<&'static str, usize> {
+        let mut graph = Graph::new();
+        graph.add_edge("a", "b", 1);
+        graph.add_edge("a", "c", 2);
+        graph.add_edge("b", "d", 3);
+        graph.add_edge("c", "d", 4);
+        graph.add_node("e");
+        graph
+    }
+
+    #[test]
+    fn topological_sort() {
+        let graph = diamond();
+        let order = graph.topological_sort().unwrap();
+        let position = |key| order.iter().position(|k| **k == key).unwrap();
+        assert_eq!(order.len(), 5);
+        assert!(position("a") < position("b"));
+        assert!(position("a") < position("c"));
+        assert!(position("b") < position("d"));
+        assert!(position("c") < position("d"));
+    }
+
+    #[test]
+    fn cycle_detection() {
+        let mut graph = diamond();
+        graph.add_edge("d", "f", 0);
+        graph.add_edge("f", "b", 0);
+        let cycle = graph.topological_sort().unwrap_err();
+        assert!(["b", "d", "f"].contains(&cycle.0));
+        assert!(graph.count_paths(&"a", &"d").is_err());
+    }
+
+    #[test]
+    fn reachability() {
+        let graph = diamond();
+        let mut reachable = graph.reachable_from(&"b").into_iter().collect::<Vec<_>>();
+        reachable.sort();
+        assert_eq!(reachable, vec![&"d"]);
+        let mut reaching = graph.reaching(&"d").into_iter().collect::<Vec<_>>();
+        reaching.sort();
+        assert_eq!(reaching, vec![&"a", &"b", &"c"]);
+        assert!(graph.reachable_from(&"e").is_empty());
+    }
+
+    #[test]
+    fn path_counting_and_fold() {
+        let graph = diamond();
+        assert_eq!(graph.count_paths(&"a", &"d"), Ok(Some(2)));
+        assert_eq!(graph.count_paths(&"e", &"d"), Ok(Some(0)));
+
+        // product of the weights along the heaviest path to a sink
+        let heaviest = graph
+            .fold(|_, successors| {
+                successors
+                    .iter()
+                    .map(|(_, weight, value)| **weight * **value)
+                    .max()
+                    .unwrap_or(1)
+            })
+            .unwrap();
+        assert_eq!(heaviest[&"a"], 8);
+        assert_eq!(heaviest[&"e"], 1);
+
+        let depth = |_: &&str, successors: &[(&&str, &usize, &usize)]| {
+            successors
+                .iter()
+                .map(|(_, _, d)| **d + 1)
+                .max()
+                .unwrap_or(0)
+        };
+        assert_eq!(graph.fold_from(&"a", depth), Ok(Some(2)));
+        assert_eq!(graph.fold_from(&"x", depth), Ok(None));
+
+        // a cycle that cannot be reached from the start is ignored
+        let mut graph = graph;
+        graph.add_edge("e", "f", 0);
+        graph.add_edge("f", "e", 0);
+        assert_eq!(graph.fold_from(&"b", depth), Ok(Some(1)));
+        assert!(graph.fold_from(&"e", depth).is_err());
+    }
+
+    #[test]
+    fn dot_export() {
+        let mut graph = Graph::new();
+        graph.add_edge("light \"red\"", "bright white", 1);
+        assert_eq!(
+            graph.to_dot(|weight: &usize| Some(weight.to_string())),
+            "digraph {
+    \"light \\\"red\\\"\";
+    \"bright white\";
+    \"light \\\"red\\\"\" -> \"bright white\" [label=\"1\"];
+}
+"
+        );
+    }
+}
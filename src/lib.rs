@@ -7,14 +7,16 @@ mod day4;
 mod day5;
 mod day6;
 mod day7;
-mod day8;
-mod day9;
-mod day10;
-mod day11;
-mod day12;
-mod day13;
-mod day14;
+pub mod day8;
+pub mod day9;
+pub mod day10;
+pub mod day11;
+pub mod day12;
+pub mod day13;
+pub mod day14;
 mod day15;
 mod day16;
+pub mod graph;
+pub mod number_theory;
 
 aoc_lib! { year = 2020 }
@@ -0,0 +1,392 @@
+use core::fmt;
+use std::collections::HashMap;
+use std::error::Error;
+use std::fmt::{Debug, Display, Formatter};
+use std::hash::Hash;
+use std::ops::{Add, Div, Mul, Rem, Sub};
+
+/// The primitive integer types the functions in this module work with.
+pub trait Integer:
+    Copy
+    + Ord
+    + Hash
+    + Debug
+    + Add<Output = Self>
+    + Sub<Output = Self>
+    + Mul<Output = Self>
+    + Div<Output = Self>
+    + Rem<Output = Self>
+{
+    const ZERO: Self;
+    const ONE: Self;
+
+    fn checked_mul(self, other: Self) -> Option<Self>;
+
+    fn rem_euclid(self, modulus: Self) -> Self;
+}
+
+/// Integer types with negative numbers, needed for Bézout coefficients.
+pub trait Signed: Integer {}
+
+macro_rules! integer {
+    ($($t:ty),*) => {
+        $(
+            impl Integer for $t {
+                const ZERO: Self = 0;
+                const ONE: Self = 1;
+
+                fn checked_mul(self, other: Self) -> Option<Self> {
+                    <$t>::checked_mul(self, other)
+                }
+
+                fn rem_euclid(self, modulus: Self) -> Self {
+                    <$t>::rem_euclid(self, modulus)
+                }
+            }
+        )*
+    };
+}
+
+macro_rules! signed {
+    ($($t:ty),*) => {
+        integer!($($t),*);
+        $(impl Signed for $t {})*
+    };
+}
+
+integer!(u8, u16, u32, u64, u128, usize);
+signed!(i8, i16, i32, i64, i128, isize);
+
+fn abs<T: Integer>(n: T) -> T {
+    if n < T::ZERO {
+        T::ZERO - n
+    } else {
+        n
+    }
+}
+
+/// Greatest common divisor, never negative.
+///
+/// Like the other functions here it overflows for arguments of signed types that equal
+/// `T::MIN`, since their absolute value does not fit into `T`.
+pub fn gcd<T: Integer>(mut a: T, mut b: T) -> T {
+    while b != T::ZERO {
+        let r = a % b;
+        a = b;
+        b = r;
+    }
+    abs(a)
+}
+
+/// Least common multiple, never negative, `None` if it does not fit into `T`.
+pub fn lcm<T: Integer>(a: T, b: T) -> Option<T> {
+    if a == T::ZERO || b == T::ZERO {
+        return Some(T::ZERO);
+    }
+    abs(a / gcd(a, b)).checked_mul(abs(b))
+}
+
+/// Returns `(d, s, t)` with `d = gcd(a, b) = s * a + t * b`.
+pub fn extended_gcd<T: Signed>(a: T, b: T) -> (T, T, T) {
+    let (mut r0, mut r1) = (a, b);
+    let (mut s0, mut s1) = (T::ONE, T::ZERO);
+    let (mut t0, mut t1) = (T::ZERO, T::ONE);
+    while r1 != T::ZERO {
+        let q = r0 / r1;
+        let (r, s, t) = (r0 - q * r1, s0 - q * s1, t0 - q * t1);
+        r0 = r1;
+        r1 = r;
+        s0 = s1;
+        s1 = s;
+        t0 = t1;
+        t1 = t;
+    }
+    if r0 < T::ZERO {
+        (T::ZERO - r0, T::ZERO - s0, T::ZERO - t0)
+    } else {
+        (r0, s0, t0)
+    }
+}
+
+/// `x` in `0..m` with `a * x ≡ 1 (mod m)`, `None` if `a` and `m` are not coprime or `m` is 0.
+pub fn mod_inverse<T: Signed>(a: T, m: T) -> Option<T> {
+    if m == T::ZERO {
+        return None;
+    }
+    let m = abs(m);
+    let (d, s, _) = extended_gcd(a.rem_euclid(m), m);
+    if d == T::ONE {
+        Some(s.rem_euclid(m))
+    } else {
+        None
+    }
+}
+
+/// `(a + b) mod m` for `a` and `b` in `0..m`, without overflowing.
+fn add_mod<T: Integer>(a: T, b: T, m: T) -> T {
+    if a >= m - b {
+        a - (m - b)
+    } else {
+        a + b
+    }
+}
+
+/// `(a * b) mod m` for `a` and `b` in `0..m`, falls back to doubling if the product overflows.
+fn mul_mod<T: Integer>(a: T, b: T, m: T) -> T {
+    if let Some(product) = a.checked_mul(b) {
+        return product % m;
+    }
+    let two = T::ONE + T::ONE;
+    let (mut a, mut b, mut result) = (a, b, T::ZERO);
+    while b != T::ZERO {
+        if b % two == T::ONE {
+            result = add_mod(result, a, m);
+        }
+        a = add_mod(a, a, m);
+        b = b / two;
+    }
+    result
+}
+
+/// `base ^ exponent mod m` in `0..m` for a non-negative exponent and positive modulus.
+pub fn mod_pow<T: Integer>(base: T, exponent: T, m: T) -> T {
+    assert!(exponent >= T::ZERO && m > T::ZERO);
+    let two = T::ONE + T::ONE;
+    let (mut base, mut exponent) = (base.rem_euclid(m), exponent);
+    let mut result = T::ONE % m;
+    while exponent != T::ZERO {
+        if exponent % two == T::ONE {
+            result = mul_mod(result, base, m);
+        }
+        base = mul_mod(base, base, m);
+        exponent = exponent / two;
+    }
+    result
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum CrtError {
+    /// moduli have to be positive
+    InvalidModulus,
+    /// no number satisfies all congruences
+    Inconsistent,
+    /// the least common multiple of the moduli does not fit into the integer type
+    Overflow,
+}
+
+impl Display for CrtError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            CrtError::InvalidModulus => write!(f, "moduli have to be positive"),
+            CrtError::Inconsistent => write!(f, "the congruences have no common solution"),
+            CrtError::Overflow => write!(f, "the solution does not fit into the integer type"),
+        }
+    }
+}
+
+impl Error for CrtError {}
+
+/// Merges `x ≡ a (mod m)` and `x ≡ b (mod n)` into `x ≡ c (mod lcm(m, n))`, returned as
+/// `(m, a)` pairs like the arguments. The moduli do not have to be coprime.
+pub fn merge_congruences<T: Signed>((m, a): (T, T), (n, b): (T, T)) -> Result<(T, T), CrtError> {
+    if m <= T::ZERO || n <= T::ZERO {
+        return Err(CrtError::InvalidModulus);
+    }
+    let (a, b) = (a.rem_euclid(m), b.rem_euclid(n));
+    let (d, s, _) = extended_gcd(m, n);
+    // a + k * m ≡ b (mod n) has a solution iff d divides the difference
+    let difference = b - a;
+    if difference % d != T::ZERO {
+        return Err(CrtError::Inconsistent);
+    }
+    // s * m ≡ d (mod n), so k = s * difference / d modulo n / d
+    let modulus = n / d;
+    let k = mul_mod(
+        (difference / d).rem_euclid(modulus),
+        s.rem_euclid(modulus),
+        modulus,
+    );
+    let lcm = m.checked_mul(modulus).ok_or(CrtError::Overflow)?;
+    // below the lcm, so neither can overflow
+    Ok((lcm, a + k * m))
+}
+
+/// Smallest non-negative `x` with `x ≡ a (mod m)` for all `(m, a)`.
+pub fn chinese_remainder<T: Signed>(congruences: &[(T, T)]) -> Result<T, CrtError> {
+    congruences
+        .iter()
+        .try_fold((T::ONE, T::ZERO), |merged, &congruence| {
+            merge_congruences(merged, congruence)
+        })
+        .map(|(_, x)| x)
+}
+
+/// Smallest `x >= 0` with `base ^ x ≡ target (mod m)` using baby-step giant-step,
+/// `base` and `m` do not have to be coprime.
+pub fn discrete_log<T: Integer>(base: T, target: T, m: T) -> Option<T> {
+    assert!(m > T::ZERO);
+    let (base, mut target, mut m) = (base.rem_euclid(m), target.rem_euclid(m), m);
+    // solves factor * base ^ x ≡ target, plus the steps needed to make base and m coprime
+    let (mut factor, mut steps) = (T::ONE % m, T::ZERO);
+    loop {
+        if factor == target {
+            return Some(steps);
+        }
+        let d = gcd(base, m);
+        if d == T::ONE {
+            break;
+        }
+        if target % d != T::ZERO {
+            return None;
+        }
+        // base ^ x ≡ target (mod m) iff base / d * base ^ (x - 1) ≡ target / d (mod m / d)
+        target = target / d;
+        m = m / d;
+        factor = mul_mod(factor % m, (base / d) % m, m);
+        steps = steps + T::ONE;
+    }
+    let base = base % m;
+
+    // n ≥ √m, at most twice as large
+    let mut n = T::ONE;
+    while n.checked_mul(n).is_some_and(|square| square < m) {
+        n = n + n;
+    }
+
+    // baby steps: target * base ^ q for q in 0..n, keeping the largest q for each value
+    let mut baby = HashMap::new();
+    let (mut q, mut value) = (T::ZERO, target);
+    while q < n {
+        baby.insert(value, q);
+        value = mul_mod(value, base, m);
+        q = q + T::ONE;
+    }
+
+    // giant steps: factor * base ^ (p * n) for p in 1..=n
+    let giant = mod_pow(base, n, m);
+    let (mut p, mut value) = (T::ONE, factor);
+    while p <= n {
+        value = mul_mod(value, giant, m);
+        if let Some(&q) = baby.get(&value) {
+            // p * n - q without the product, which can overflow even if the result does not
+            return Some((p - T::ONE) * n + (n - q) + steps);
+        }
+        p = p + T::ONE;
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn gcd_and_lcm() {
+        assert_eq!(gcd(12u8, 18), 6);
+        assert_eq!(gcd(-12i32, 18), 6);
+        assert_eq!(gcd(0i64, -7), 7);
+        assert_eq!(lcm(4usize, 6), Some(12));
+        assert_eq!(lcm(-4i16, 6), Some(12));
+        assert_eq!(lcm(0u32, 6), Some(0));
+        assert_eq!(lcm(200u8, 3), None);
+
+        for (a, b) in [(240i64, 46), (-240, 46), (17, 5), (0, 9), (9, 0)] {
+            let (d, s, t) = extended_gcd(a, b);
+            assert_eq!(d, gcd(a, b));
+            assert_eq!(s * a + t * b, d);
+        }
+    }
+
+    #[test]
+    fn modular_arithmetic() {
+        assert_eq!(mod_inverse(3i32, 11), Some(4));
+        assert_eq!(mod_inverse(-3i32, 11), Some(7));
+        assert_eq!(mod_inverse(6i32, 9), None);
+        assert_eq!(mod_inverse(3i32, 0), None);
+        assert_eq!(mod_inverse(3i32, -11), Some(4));
+
+        assert_eq!(mod_pow(2u32, 10, 1000), 24);
+        assert_eq!(mod_pow(7i8, 0, 13), 1);
+        assert_eq!(mod_pow(5u8, 3, 1), 0);
+        // the squares overflow u64, the result does not
+        let p = 18_446_744_073_709_551_557u64;
+        assert_eq!(mod_pow(3, p - 1, p), 1);
+        assert_eq!(mod_pow(i128::MAX - 1, 2, i128::MAX), 1);
+    }
+
+    #[test]
+    fn chinese_remainder_theorem() {
+        assert_eq!(chinese_remainder(&[(3i32, 2), (4, 3), (5, 2)]), Ok(47));
+        assert_eq!(chinese_remainder::<i64>(&[]), Ok(0));
+        assert_eq!(chinese_remainder(&[(12i64, 5), (18, 11)]), Ok(29));
+        assert_eq!(chinese_remainder(&[(6i8, -3), (3, 0)]), Ok(3));
+        assert_eq!(
+            chinese_remainder(&[(12i64, 5), (18, 10)]),
+            Err(CrtError::Inconsistent)
+        );
+        assert_eq!(
+            chinese_remainder(&[(0i64, 5)]),
+            Err(CrtError::InvalidModulus)
+        );
+        assert_eq!(
+            chinese_remainder(&[(11i8, 1), (13, 1)]),
+            Err(CrtError::Overflow)
+        );
+        assert_eq!(merge_congruences((4i32, 1), (6, 3)), Ok((12, 9)));
+    }
+
+    #[test]
+    fn discrete_logarithm() {
+        assert_eq!(discrete_log(3u64, 13, 17), Some(4));
+        assert_eq!(discrete_log(2u64, 1, 7), Some(0));
+        assert_eq!(discrete_log(2u64, 3, 7), None);
+        // the base is not coprime to the modulus
+        assert_eq!(discrete_log(2u32, 8, 24), Some(3));
+        assert_eq!(discrete_log(2u32, 16, 24), Some(4));
+        assert_eq!(discrete_log(6i32, 0, 36), Some(2));
+        assert_eq!(discrete_log(2u32, 3, 24), None);
+
+        for m in 1u32..40 {
+            for base in 0..m {
+                for target in 0..m {
+                    let smallest = (0..2 * m).find(|&x| mod_pow(base, x, m) == target % m);
+                    assert_eq!(
+                        discrete_log(base, target, m),
+                        smallest,
+                        "{}^x ≡ {} (mod {})",
+                        base,
+                        target,
+                        m
+                    );
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn discrete_logarithm_near_type_limit() {
+        let x = discrete_log(6u8, 7, 251).unwrap();
+        assert_eq!(mod_pow(6, x, 251), 7);
+        for &m in &[251u8, 254, 255] {
+            for base in 0..m {
+                // first exponent for each value of base ^ x
+                let mut first = [None; 256];
+                let mut value = 1 % m;
+                for x in 0..=u8::MAX {
+                    first[value as usize].get_or_insert(x);
+                    value = mul_mod(value, base, m);
+                }
+                for target in 0..m {
+                    assert_eq!(
+                        discrete_log(base, target, m),
+                        first[target as usize],
+                        "{}^x ≡ {} (mod {})",
+                        base,
+                        target,
+                        m
+                    );
+                }
+            }
+        }
+    }
+}